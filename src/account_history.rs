@@ -0,0 +1,157 @@
+use crate::block::{collapse_row_deltas, DecodedRow};
+use crate::log_writer::LogWriter;
+use alloy::primitives::Address;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Answer of the account history for a native name or account index at a given block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryLookup {
+    Found(Address),
+    /// The account row was removed before the block
+    Absent,
+    /// No row recorded up to the block, the account may predate the history
+    Unknown,
+}
+
+/// One line of the history file, written every time a name or index changes address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoryRecord {
+    block_num: u32,
+    index: u64,
+    name: u64,
+    address: Option<Address>,
+}
+
+/// Name and index to address mappings of the eosio.evm account table over the translated
+/// blocks, optionally persisted as an append only log so it survives restarts
+#[derive(Debug, Default)]
+pub struct AccountHistory {
+    names: HashMap<u64, Vec<(u32, Option<Address>)>>,
+    indexes: HashMap<u64, Vec<(u32, Option<Address>)>>,
+    last_block: Option<u32>,
+    log: Option<LogWriter>,
+}
+
+impl AccountHistory {
+    /// Loads the history already written to `path` and appends new records to it
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut history = AccountHistory::default();
+        if path.exists() {
+            let file = File::open(path).wrap_err("Failed to open account history")?;
+            for line in BufReader::new(file).lines() {
+                let record: HistoryRecord =
+                    serde_json::from_str(&line?).wrap_err("Invalid record in account history")?;
+                history.insert(record);
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err("Failed to open account history for writing")?;
+        history.log = Some(LogWriter::new(file));
+        Ok(history)
+    }
+
+    /// Last block recorded, blocks up to it are ignored when translated again
+    pub fn last_block(&self) -> Option<u32> {
+        self.last_block
+    }
+
+    /// Records the account rows of a block. Removals take effect at the next block, so lookups
+    /// for actions of the block still see the removed account. The log is written in the
+    /// background, see `check_writes`.
+    pub fn record_block(&mut self, block_num: u32, rows: &[DecodedRow]) {
+        if self.last_block.is_some_and(|last| block_num <= last) {
+            return;
+        }
+        for row in collapse_row_deltas(rows) {
+            if let DecodedRow::Account { present, row } = row {
+                let record = HistoryRecord {
+                    block_num: if present { block_num } else { block_num + 1 },
+                    index: row.index,
+                    name: row.account.value(),
+                    address: present.then(|| Address::from(row.address.data)),
+                };
+                if self.insert(record.clone()) {
+                    if let Some(log) = &self.log {
+                        log.append(&record);
+                    }
+                }
+            }
+        }
+        self.last_block = Some(block_num);
+        if let Some(log) = &self.log {
+            log.flush();
+        }
+    }
+
+    /// Fails once writing the log failed, the records after the failure are not persisted
+    pub fn check_writes(&self) -> Result<()> {
+        self.log.as_ref().map_or(Ok(()), LogWriter::check)
+    }
+
+    /// Returns false if the record didn't change any mapping
+    fn insert(&mut self, record: HistoryRecord) -> bool {
+        let mut changed = push_change(
+            self.indexes.entry(record.index).or_default(),
+            record.block_num,
+            record.address,
+        );
+        // Rows created from the EVM side have no name
+        if record.name != 0 {
+            changed |= push_change(
+                self.names.entry(record.name).or_default(),
+                record.block_num,
+                record.address,
+            );
+        }
+        let block_num = record
+            .block_num
+            .saturating_sub(record.address.is_none() as u32);
+        self.last_block = Some(
+            self.last_block
+                .map_or(block_num, |last| last.max(block_num)),
+        );
+        changed
+    }
+
+    pub fn address_by_name(&self, name: u64, block_num: u32) -> HistoryLookup {
+        lookup(self.names.get(&name), block_num)
+    }
+
+    pub fn address_by_index(&self, index: u64, block_num: u32) -> HistoryLookup {
+        lookup(self.indexes.get(&index), block_num)
+    }
+}
+
+fn push_change(
+    changes: &mut Vec<(u32, Option<Address>)>,
+    block_num: u32,
+    address: Option<Address>,
+) -> bool {
+    if changes.last().map(|(_, last)| *last) == Some(address) {
+        return false;
+    }
+    changes.push((block_num, address));
+    true
+}
+
+fn lookup(changes: Option<&Vec<(u32, Option<Address>)>>, block_num: u32) -> HistoryLookup {
+    let changes = match changes {
+        Some(changes) => changes,
+        None => return HistoryLookup::Unknown,
+    };
+    let position = changes.partition_point(|(change_block, _)| *change_block <= block_num);
+    match position.checked_sub(1).map(|i| changes[i].1) {
+        Some(Some(address)) => HistoryLookup::Found(address),
+        Some(None) => HistoryLookup::Absent,
+        None => HistoryLookup::Unknown,
+    }
+}
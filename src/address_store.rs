@@ -0,0 +1,122 @@
+use crate::log_writer::LogWriter;
+use alloy::primitives::Address;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// One line of the address store file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StoredAddress {
+    /// `index` is only known when the mapping comes from an account table row
+    Name {
+        name: u64,
+        index: Option<u64>,
+        address: Address,
+    },
+    Index {
+        index: u64,
+        address: Address,
+    },
+    /// The account row of the name was removed
+    NameRemoved {
+        name: u64,
+    },
+    IndexRemoved {
+        index: u64,
+    },
+}
+
+impl StoredAddress {
+    fn key(&self) -> (bool, u64) {
+        match self {
+            StoredAddress::Name { name, .. } | StoredAddress::NameRemoved { name } => (true, *name),
+            StoredAddress::Index { index, .. } | StoredAddress::IndexRemoved { index } => {
+                (false, *index)
+            }
+        }
+    }
+
+    fn is_removal(&self) -> bool {
+        matches!(
+            self,
+            StoredAddress::NameRemoved { .. } | StoredAddress::IndexRemoved { .. }
+        )
+    }
+}
+
+/// Append only file of the addresses resolved by the address cache, so a restarted translator
+/// doesn't have to query them again. Entries are only held in memory until they are taken.
+pub struct AddressStore {
+    entries: Vec<StoredAddress>,
+    log: LogWriter,
+}
+
+impl AddressStore {
+    /// Loads the file, rewriting it with only the last entry of every name and index and
+    /// without the removed ones
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut entries = vec![];
+        if path.exists() {
+            let file = File::open(path).wrap_err("Failed to open address store")?;
+            let mut positions = HashMap::new();
+            for line in BufReader::new(file).lines() {
+                let entry: StoredAddress =
+                    serde_json::from_str(&line?).wrap_err("Invalid entry in address store")?;
+                match positions.get(&entry.key()) {
+                    Some(&position) => entries[position] = entry,
+                    None => {
+                        positions.insert(entry.key(), entries.len());
+                        entries.push(entry);
+                    }
+                }
+            }
+            entries.retain(|entry| !entry.is_removal());
+
+            let mut compacted = path.as_os_str().to_owned();
+            compacted.push(".tmp");
+            let compacted = PathBuf::from(compacted);
+            let mut writer = BufWriter::new(File::create(&compacted)?);
+            for entry in &entries {
+                serde_json::to_writer(&mut writer, entry)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+            fs::rename(&compacted, path).wrap_err("Failed to compact address store")?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err("Failed to open address store for writing")?;
+        Ok(AddressStore {
+            entries,
+            log: LogWriter::new(file),
+        })
+    }
+
+    /// Entries loaded from the file, in the order they were first written. Only returned once,
+    /// the store doesn't keep them.
+    pub fn take_entries(&mut self) -> Vec<StoredAddress> {
+        std::mem::take(&mut self.entries)
+    }
+
+    /// Queues the entry, the file is written in the background
+    pub fn append(&self, entry: &StoredAddress) {
+        self.log.append(entry);
+    }
+
+    pub fn flush(&self) {
+        self.log.flush();
+    }
+
+    /// Fails once writing the file failed, the entries after the failure are not persisted
+    pub fn check_writes(&self) -> Result<()> {
+        self.log.check()
+    }
+}
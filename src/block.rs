@@ -1,29 +1,37 @@
-use crate::transaction::TelosEVMTransaction;
+use crate::chain_spec::TelosChainSpec;
+use crate::duplicate_hashes::TxPosition;
+use crate::resolver::{AddressResolver, ResolveError};
+use crate::transaction::{parse_deposit_memo, NativeOrigin, TelosEVMTransaction};
+use crate::translator::ChainIdException;
 use crate::types::env::{ANTELOPE_EPOCH_MS, ANTELOPE_INTERVAL_MS};
 use crate::types::evm_types::{
     AccountRow, AccountStateRow, CreateAction, EvmContractConfigRow, OpenWalletAction,
-    PrintedReceipt, RawAction, SetRevisionAction, TransferAction, WithdrawAction,
+    PrintedReceipt, PrintedReceiptError, RawAction, SetRevisionAction, TransferAction,
+    WithdrawAction,
 };
 use crate::types::names::*;
 use crate::types::ship_types::{
-    ActionTrace, ContractRow, GetBlocksResultV0, SignedBlock, TableDelta, TransactionTrace,
+    transaction_status, Account, AccountMetadata, ActionReceipt, ActionTrace, Code, ContractRow,
+    GetBlocksResultV0, PermissionLevel, SignedBlock, TableDelta, TransactionTrace,
 };
-use crate::types::translator_types::NameToAddressCache;
-use alloy::primitives::{Bloom, Bytes, FixedBytes, B256, U256};
+use alloy::primitives::{keccak256, Address, Bloom, Bytes, FixedBytes, B256, U256};
 use alloy_consensus::constants::{EMPTY_OMMER_ROOT_HASH, EMPTY_ROOT_HASH};
 use alloy_consensus::{Header, TxEnvelope};
 use alloy_rlp::{encode, Encodable};
+use antelope::chain::asset::Symbol;
 use antelope::chain::checksum::Checksum256;
 use antelope::chain::name::Name;
 use antelope::serializer::Packer;
 use reth_primitives::ReceiptWithBloom;
 use reth_rpc_types::ExecutionPayloadV1;
-use reth_telos_rpc_engine_api::structs::TelosEngineAPIExtraFields;
+use reth_telos_rpc_engine_api::structs::{
+    TelosAccountStateTableRow, TelosAccountTableRow, TelosEngineAPIExtraFields,
+};
 use reth_trie_common::root::ordered_trie_root_with_encoder;
 use std::cmp::Ordering;
-use tracing::warn;
-
-const MINIMUM_FEE_PER_GAS: u128 = 7;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use tracing::{debug, warn};
 
 pub trait BasicTrace {
     fn action_name(&self) -> u64;
@@ -31,6 +39,11 @@ pub trait BasicTrace {
     fn receiver(&self) -> u64;
     fn console(&self) -> String;
     fn data(&self) -> Vec<u8>;
+    fn receipt(&self) -> Option<ActionReceipt>;
+    fn except(&self) -> Option<String>;
+    fn global_sequence(&self) -> u64;
+    fn action_ordinal(&self) -> u32;
+    fn authorization(&self) -> Vec<PermissionLevel>;
 }
 
 #[derive(Clone)]
@@ -74,13 +87,352 @@ impl BasicTrace for ActionTrace {
             ActionTrace::V1(a) => a.act.data.clone(),
         }
     }
+
+    fn receipt(&self) -> Option<ActionReceipt> {
+        match self {
+            ActionTrace::V0(a) => a.receipt.clone(),
+            ActionTrace::V1(a) => a.receipt.clone(),
+        }
+    }
+
+    fn except(&self) -> Option<String> {
+        match self {
+            ActionTrace::V0(a) => a.except.clone(),
+            ActionTrace::V1(a) => a.except.clone(),
+        }
+    }
+
+    fn global_sequence(&self) -> u64 {
+        let receipt = match self {
+            ActionTrace::V0(a) => &a.receipt,
+            ActionTrace::V1(a) => &a.receipt,
+        };
+        match receipt {
+            Some(ActionReceipt::V0(receipt)) => receipt.global_sequence,
+            None => 0,
+        }
+    }
+
+    fn action_ordinal(&self) -> u32 {
+        match self {
+            ActionTrace::V0(a) => a.action_ordinal.n,
+            ActionTrace::V1(a) => a.action_ordinal.n,
+        }
+    }
+
+    fn authorization(&self) -> Vec<PermissionLevel> {
+        match self {
+            ActionTrace::V0(a) => a.act.authorization.clone(),
+            ActionTrace::V1(a) => a.act.authorization.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranslatorWarning {
+    /// eosio.evm::raw action flagged as a gas estimation that was not reverted
+    GasEstimation { block_num: u32, raw_tx_hash: B256 },
+    /// Transfer to eosio.evm of another token than 4,TLOS, not translated
+    UnexpectedDepositSymbol { block_num: u32, from: Name },
+    /// Signed transaction with a chain id other than the configured one
+    ChainIdMismatch {
+        block_num: u32,
+        tx_hash: B256,
+        chain_id: Option<u64>,
+    },
+    /// Gas used or count of the raw transactions differ from the eosio.evm config row counters,
+    /// deposits and withdraws are not counted by either
+    ConfigRowMismatch {
+        block_num: u32,
+        gas_used: u64,
+        contract_gas_used: U256,
+        transactions: usize,
+        contract_trx_index: u32,
+    },
+    /// Contract address printed in a receipt differs from the CREATE derivation of the
+    /// transaction sender and nonce, `expected` is `None` when the transaction is not a create
+    PrintedCreatedAddressMismatch {
+        block_num: u32,
+        tx_hash: B256,
+        printed: Address,
+        expected: Option<Address>,
+    },
+    /// Position of a raw transaction among the raw transactions of the block differs from the
+    /// trx_index printed by the contract, deposits and withdraws are not counted by either
+    TrxIndexMismatch {
+        block_num: u32,
+        printed: u16,
+        translated: usize,
+    },
+    /// Transaction hash already produced by an earlier transaction of the duplicate hash window
+    DuplicateTxHash {
+        tx_hash: B256,
+        first: TxPosition,
+        duplicate: TxPosition,
+    },
+    /// eosio.evm code changed to a hash missing from the known contract hashes
+    UnknownContractCode { block_num: u32, code_hash: B256 },
+    /// eosio.evm ABI changed to one whose keccak256 hash is missing from the known ABI hashes
+    UnknownContractAbi { block_num: u32, abi_hash: B256 },
+    /// Address derived for an eosio.evm::create account differs from its account table row
+    CreateAddressMismatch {
+        block_num: u32,
+        account: Name,
+        derived: Address,
+        table: Address,
+    },
+}
+
+impl fmt::Display for TranslatorWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranslatorWarning::GasEstimation {
+                block_num,
+                raw_tx_hash,
+            } => write!(
+                f,
+                "Gas estimation raw action for tx {raw_tx_hash} was not reverted in block {block_num}"
+            ),
+            TranslatorWarning::UnexpectedDepositSymbol { block_num, from } => write!(
+                f,
+                "Skipped transfer from {from} to eosio.evm of a token other than 4,TLOS in block {block_num}"
+            ),
+            TranslatorWarning::ChainIdMismatch {
+                block_num,
+                tx_hash,
+                chain_id,
+            } => write!(
+                f,
+                "Transaction {tx_hash} signed with chain id {chain_id:?} in block {block_num}"
+            ),
+            TranslatorWarning::ConfigRowMismatch {
+                block_num,
+                gas_used,
+                contract_gas_used,
+                transactions,
+                contract_trx_index,
+            } => write!(
+                f,
+                "Block {block_num} translated {transactions} transactions using {gas_used} gas, config row has trx_index {contract_trx_index} and gas_used_block {contract_gas_used}"
+            ),
+            TranslatorWarning::PrintedCreatedAddressMismatch {
+                block_num,
+                tx_hash,
+                printed,
+                expected,
+            } => write!(
+                f,
+                "Transaction {tx_hash} printed created address {printed}, expected {expected:?} in block {block_num}"
+            ),
+            TranslatorWarning::TrxIndexMismatch {
+                block_num,
+                printed,
+                translated,
+            } => write!(
+                f,
+                "Printed trx_index {printed} does not match translated index {translated} in block {block_num}"
+            ),
+            TranslatorWarning::DuplicateTxHash {
+                tx_hash,
+                first,
+                duplicate,
+            } => write!(
+                f,
+                "Transaction {tx_hash} at index {} of block {} was already translated at index {} of block {}",
+                duplicate.tx_index, duplicate.block_num, first.tx_index, first.block_num
+            ),
+            TranslatorWarning::UnknownContractCode {
+                block_num,
+                code_hash,
+            } => write!(
+                f,
+                "eosio.evm code changed to unknown hash {code_hash} in block {block_num}"
+            ),
+            TranslatorWarning::UnknownContractAbi {
+                block_num,
+                abi_hash,
+            } => write!(
+                f,
+                "eosio.evm ABI changed to unknown hash {abi_hash} in block {block_num}"
+            ),
+            TranslatorWarning::CreateAddressMismatch {
+                block_num,
+                account,
+                derived,
+                table,
+            } => write!(
+                f,
+                "Address {derived} derived for created account {account} does not match {table} from the account table in block {block_num}"
+            ),
+        }
+    }
+}
+
+/// A block that could not be translated
+#[derive(Debug)]
+pub enum TranslationError {
+    Resolve(ResolveError),
+    /// A raw action without a valid receipt in its console
+    PrintedReceipt {
+        block_num: u32,
+        error: PrintedReceiptError,
+    },
+}
+
+impl fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranslationError::Resolve(error) => write!(f, "{error}"),
+            TranslationError::PrintedReceipt { block_num, error } => {
+                write!(f, "{error} for raw action in block {block_num}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TranslationError {}
+
+impl From<ResolveError> for TranslationError {
+    fn from(error: ResolveError) -> Self {
+        TranslationError::Resolve(error)
+    }
 }
 
 #[derive(Clone)]
 pub enum DecodedRow {
     Config(EvmContractConfigRow),
-    Account(AccountRow),
-    AccountState(AccountStateRow),
+    Account {
+        present: bool,
+        row: AccountRow,
+    },
+    /// The accountstate table is scoped by the index of the account owning the slot,
+    /// `row.index` is only the primary key of the row inside that scope
+    AccountState {
+        present: bool,
+        account_index: u64,
+        row: AccountStateRow,
+    },
+}
+
+/// Keeps only the last write of every account and storage slot, in the order of those writes
+pub fn collapse_row_deltas(rows: &[DecodedRow]) -> Vec<DecodedRow> {
+    let mut seen_accounts = HashSet::new();
+    let mut seen_slots = HashSet::new();
+    let mut collapsed = rows
+        .iter()
+        .rev()
+        .filter(|row| match row {
+            DecodedRow::Config(_) => true,
+            DecodedRow::Account { row, .. } => seen_accounts.insert(row.index),
+            DecodedRow::AccountState {
+                account_index, row, ..
+            } => seen_slots.insert((*account_index, row.key.data)),
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    collapsed.reverse();
+    collapsed
+}
+
+/// Account table change, a removed row is a removal instead of its last value
+#[derive(Clone)]
+pub enum AccountDiff {
+    Updated(TelosAccountTableRow),
+    Removed { address: Address, account: Name },
+}
+
+impl AccountDiff {
+    pub fn address(&self) -> Address {
+        match self {
+            AccountDiff::Updated(row) => row.address,
+            AccountDiff::Removed { address, .. } => *address,
+        }
+    }
+
+    /// Engine API row, its format has no removal flag so a removed account is sent as an
+    /// empty account, which EIP-161 treats as nonexistent
+    pub fn to_table_row(&self) -> TelosAccountTableRow {
+        match self {
+            AccountDiff::Updated(row) => row.clone(),
+            AccountDiff::Removed { address, account } => TelosAccountTableRow {
+                address: *address,
+                account: account.to_string(),
+                nonce: 0,
+                code: Bytes::new(),
+                balance: U256::ZERO,
+            },
+        }
+    }
+}
+
+pub fn account_diff(row: &AccountRow, present: bool) -> AccountDiff {
+    let address = Address::from_slice(&row.address.data);
+    if !present {
+        return AccountDiff::Removed {
+            address,
+            account: row.account,
+        };
+    }
+    AccountDiff::Updated(TelosAccountTableRow {
+        address,
+        account: row.account.to_string(),
+        nonce: row.nonce,
+        code: Bytes::from(row.code.clone()),
+        balance: U256::from_be_slice(&row.balance.data),
+    })
+}
+
+/// Storage diff, removed rows are sent as a zero value, same as clearing the slot with SSTORE
+pub fn account_state_diff(
+    address: Address,
+    row: &AccountStateRow,
+    present: bool,
+) -> TelosAccountStateTableRow {
+    TelosAccountStateTableRow {
+        address,
+        key: U256::from_be_slice(&row.key.data),
+        value: if present {
+            U256::from_be_slice(&row.value.data)
+        } else {
+            U256::ZERO
+        },
+    }
+}
+
+/// Account and storage changes of a block, only the last write of every key
+#[derive(Clone, Default)]
+pub struct StateDiffs {
+    pub accounts: Vec<AccountDiff>,
+    pub account_states: Vec<TelosAccountStateTableRow>,
+}
+
+impl StateDiffs {
+    /// Addresses of the accounts whose row was removed
+    pub fn removed_accounts(&self) -> Vec<Address> {
+        self.accounts
+            .iter()
+            .filter(|diff| matches!(diff, AccountDiff::Removed { .. }))
+            .map(AccountDiff::address)
+            .collect()
+    }
+}
+
+/// Native names and account indexes a block will need resolved while being finalized
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressLookups {
+    pub names: BTreeSet<u64>,
+    pub indexes: BTreeSet<u64>,
+}
+
+impl AddressLookups {
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.indexes.is_empty()
+    }
+
+    pub fn extend(&mut self, other: AddressLookups) {
+        self.names.extend(other.names);
+        self.indexes.extend(other.indexes);
+    }
 }
 
 #[derive(Clone)]
@@ -91,13 +443,23 @@ pub struct ProcessingEVMBlock {
     result: GetBlocksResultV0,
     signed_block: Option<SignedBlock>,
     block_traces: Option<Vec<TransactionTrace>>,
-    contract_rows: Option<Vec<ContractRow>>,
+    contract_rows: Option<Vec<(bool, ContractRow)>>,
+    /// Set once deserialized, when the block changes the eosio.evm code or ABI
+    pub contract_update: Option<ContractUpdate>,
     cumulative_gas_used: u64,
+    revision: u64,
     pub decoded_rows: Vec<DecodedRow>,
     pub transactions: Vec<(TelosEVMTransaction, ReceiptWithBloom)>,
     pub new_gas_price: Option<(u64, U256)>,
     pub new_revision: Option<(u64, u64)>,
     pub new_wallets: Vec<WalletEvents>,
+    pub warnings: Vec<TranslatorWarning>,
+    pub skipped_transactions: u32,
+    pub skipped_actions: u32,
+    /// Raw EVM transactions translated so far, the counter behind the printed trx_index
+    pub raw_transactions: usize,
+    /// Gas used by the raw EVM transactions, without deposits and withdraws
+    pub raw_gas_used: u64,
     pub lib_num: u32,
     pub lib_hash: Checksum256,
 }
@@ -106,12 +468,80 @@ pub struct ProcessingEVMBlock {
 pub struct TelosEVMBlock {
     pub block_num: u32,
     pub block_hash: B256,
+    /// Native block the EVM block was translated from
+    pub native_block_num: u32,
+    pub native_block_id: B256,
     pub lib_num: u32,
     pub lib_hash: B256,
     pub header: Header,
     pub transactions: Vec<(TelosEVMTransaction, ReceiptWithBloom)>,
     pub execution_payload: ExecutionPayloadV1,
     pub extra_fields: TelosEngineAPIExtraFields,
+    /// Accounts whose row was removed, also in the state diffs as empty accounts
+    pub removed_accounts: Vec<Address>,
+    pub warnings: Vec<TranslatorWarning>,
+    /// State root computed from the table deltas, when state root computation is not disabled
+    pub computed_state_root: Option<B256>,
+    pub contract_update: Option<ContractUpdate>,
+}
+
+/// eosio.evm code or ABI change, the layouts the translator decodes may have changed with it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractUpdate {
+    /// Code hash from the account metadata row, zero when the code was cleared. Also set when
+    /// the row changed for another reason than a new code, privileges for instance.
+    pub code_hash: Option<B256>,
+    /// Wasm of the new code, when the block carries its code row
+    pub code: Option<Bytes>,
+    /// Packed ABI from the account row
+    pub abi: Option<Bytes>,
+}
+
+/// Owner of a packed account or account_metadata row, the name follows the variant index
+fn row_owner(data: &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(1..9)?.try_into().ok()?))
+}
+
+/// Hash of a packed code row, after the variant index, vm type and vm version
+fn row_code_hash(data: &[u8]) -> Option<B256> {
+    data.get(3..35).map(B256::from_slice)
+}
+
+/// eosio.evm rows of the account, account_metadata and code deltas, rows of other accounts
+/// and other code are skipped before being decoded
+fn contract_update(deltas: &[TableDelta]) -> Option<ContractUpdate> {
+    let mut update = ContractUpdate::default();
+    let mut codes = vec![];
+    for TableDelta::V0(delta) in deltas {
+        for row in delta.rows.iter().filter(|row| row.present) {
+            match delta.name.as_str() {
+                "account" if row_owner(&row.data) == Some(EOSIO_EVM) => {
+                    let Account::V0(account) = decode(&row.data);
+                    update.abi = Some(Bytes::from(account.abi));
+                }
+                "account_metadata" if row_owner(&row.data) == Some(EOSIO_EVM) => {
+                    let AccountMetadata::V0(metadata) = decode(&row.data);
+                    update.code_hash = Some(
+                        metadata
+                            .code
+                            .map(|code| B256::from(code.code_hash.data))
+                            .unwrap_or_default(),
+                    );
+                }
+                // Code rows are keyed by hash, matched with the metadata once all are read
+                "code" => codes.push(row.data.as_slice()),
+                _ => (),
+            }
+        }
+    }
+    update.code = update.code_hash.and_then(|code_hash| {
+        let data = codes
+            .into_iter()
+            .find(|data| row_code_hash(data) == Some(code_hash))?;
+        let Code::V0(code) = decode(data);
+        Some(Bytes::from(code.code))
+    });
+    (update != ContractUpdate::default()).then_some(update)
 }
 
 pub fn decode<T: Packer + Default>(raw: &[u8]) -> T {
@@ -139,16 +569,28 @@ impl ProcessingEVMBlock {
             signed_block: None,
             block_traces: None,
             contract_rows: None,
+            contract_update: None,
             cumulative_gas_used: 0,
+            revision: 0,
             decoded_rows: vec![],
             transactions: vec![],
 
             new_gas_price: None,
             new_revision: None,
             new_wallets: vec![],
+            warnings: vec![],
+            skipped_transactions: 0,
+            skipped_actions: 0,
+            raw_transactions: 0,
+            raw_gas_used: 0,
         }
     }
 
+    /// Id of the native block
+    pub fn block_id(&self) -> B256 {
+        B256::from(self.block_hash.data)
+    }
+
     pub fn deserialize(&mut self) {
         self.signed_block = self.result.block.as_deref().map(decode);
 
@@ -162,17 +604,234 @@ impl ProcessingEVMBlock {
             warn!("No deltas found for block: {}", self.block_num);
         };
 
-        // TODO: Handle present: false here?  How to account for empty/deleted rows?
-        self.contract_rows = self.result.deltas.as_deref().map(|deltas| {
-            decode::<Vec<TableDelta>>(deltas)
+        let deltas = self.result.deltas.as_deref().map(decode::<Vec<TableDelta>>);
+        self.contract_update = deltas.as_deref().and_then(contract_update);
+
+        // Removed rows (present: false) carry the last value of the row
+        self.contract_rows = deltas.map(|deltas| {
+            deltas
                 .iter()
                 .filter(|TableDelta::V0(delta)| delta.name == "contract_row")
                 .map(|TableDelta::V0(delta)| delta.rows.as_slice())
-                .flat_map(|rows| rows.iter().map(|row| row.data.as_slice()).map(decode))
-                .collect::<Vec<ContractRow>>()
+                .flat_map(|rows| {
+                    rows.iter()
+                        .map(|row| (row.present, decode(row.data.as_slice())))
+                })
+                .collect::<Vec<(bool, ContractRow)>>()
         });
     }
 
+    /// Names and indexes the block links to an address itself, with its account rows and
+    /// openwallet actions. Only available once the block is deserialized.
+    pub fn linked_accounts(&self) -> AddressLookups {
+        let mut linked = AddressLookups::default();
+        for (_present, ContractRow::V0(row)) in self.contract_rows.iter().flatten() {
+            if row.code.value() == EOSIO_EVM && row.table == Name::new_from_str("account") {
+                let account: AccountRow = decode(&row.value);
+                linked.names.insert(account.account.value());
+                linked.indexes.insert(account.index);
+            }
+        }
+        for trace in self.executed_action_traces() {
+            if trace.action_account() == EOSIO_EVM && trace.action_name() == OPENWALLET {
+                let wallet_action: OpenWalletAction = decode(&trace.data());
+                linked.names.insert(wallet_action.account.value());
+            }
+        }
+        linked
+    }
+
+    /// Traces of the actions that went through, in transactions that were executed
+    fn executed_action_traces(&self) -> impl Iterator<Item = &ActionTrace> {
+        self.block_traces
+            .iter()
+            .flatten()
+            .filter(|TransactionTrace::V0(trace)| {
+                trace.status == transaction_status::EXECUTED && trace.except.is_none()
+            })
+            .flat_map(|TransactionTrace::V0(trace)| &trace.action_traces)
+            .filter(|action| action.receipt().is_some() && action.except().is_none())
+    }
+
+    /// Names and indexes looked up by the transfer and withdraw actions and the
+    /// accountstate rows of the block, without the accounts in `linked`. Pass the
+    /// `linked_accounts` of this block and of the earlier blocks resolved together with it.
+    /// Only available once the block is deserialized.
+    pub fn address_lookups(&self, linked: &AddressLookups) -> AddressLookups {
+        let mut lookups = AddressLookups::default();
+
+        for (_present, ContractRow::V0(row)) in self.contract_rows.iter().flatten() {
+            if row.code.value() == EOSIO_EVM && row.table == Name::new_from_str("accountstate") {
+                lookups.indexes.insert(row.scope.value());
+            }
+        }
+
+        for trace in self.executed_action_traces() {
+            let action_account = trace.action_account();
+            let action_name = trace.action_name();
+            if action_account == EOSIO_EVM && action_name == WITHDRAW {
+                let withdraw_action: WithdrawAction = decode(&trace.data());
+                lookups.names.insert(withdraw_action.to.value());
+            } else if action_account == EOSIO_TOKEN
+                && action_name == TRANSFER
+                && trace.receiver() == EOSIO_EVM
+            {
+                let transfer_action: TransferAction = decode(&trace.data());
+                if transfer_action.to.n == EOSIO_EVM
+                    && !SYSTEM_ACCOUNTS.contains(&transfer_action.from.n)
+                    && parse_deposit_memo(&transfer_action.memo).is_none()
+                {
+                    lookups.names.insert(transfer_action.from.n);
+                }
+            }
+        }
+
+        lookups.names.retain(|name| !linked.names.contains(name));
+        lookups
+            .indexes
+            .retain(|index| !linked.indexes.contains(index));
+        lookups
+    }
+
+    /// Diffs of the decoded account and accountstate rows, storage rows are resolved to the
+    /// address of the account owning their scope. The account rows of the block come first, a
+    /// removed row still holds the address, `resolver` is only asked for the other accounts.
+    /// Only available once translated.
+    pub async fn state_diffs(
+        &self,
+        resolver: &impl AddressResolver,
+    ) -> Result<StateDiffs, ResolveError> {
+        let block_accounts = self
+            .decoded_rows
+            .iter()
+            .filter_map(|row| match row {
+                DecodedRow::Account { row, .. } => {
+                    Some((row.index, Address::from(row.address.data)))
+                }
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let mut diffs = StateDiffs::default();
+        for row in collapse_row_deltas(&self.decoded_rows) {
+            match row {
+                DecodedRow::Account { present, row } => {
+                    diffs.accounts.push(account_diff(&row, present))
+                }
+                DecodedRow::AccountState {
+                    present,
+                    account_index,
+                    row,
+                } => {
+                    let address = match block_accounts.get(&account_index) {
+                        Some(address) => Some(*address),
+                        None => {
+                            resolver
+                                .address_by_index(account_index, self.block_num)
+                                .await?
+                        }
+                    };
+                    let address = address.ok_or(ResolveError::UnknownIndex {
+                        index: account_index,
+                        block_num: self.block_num,
+                    })?;
+                    diffs
+                        .account_states
+                        .push(account_state_diff(address, &row, present));
+                }
+                DecodedRow::Config(_) => (),
+            }
+        }
+        Ok(diffs)
+    }
+
+    /// Signed transactions of the block whose chain id differs from the translator's one
+    pub fn chain_id_violations(&self, exceptions: &[ChainIdException]) -> Vec<TranslatorWarning> {
+        self.transactions
+            .iter()
+            .filter_map(|(transaction, _receipt)| {
+                let chain_id = transaction.signed_chain_id()?;
+                if chain_id == Some(self.chain_id)
+                    || exceptions
+                        .iter()
+                        .any(|exception| exception.allows(self.block_num, chain_id))
+                {
+                    return None;
+                }
+                Some(TranslatorWarning::ChainIdMismatch {
+                    block_num: self.block_num,
+                    tx_hash: *transaction.hash(),
+                    chain_id,
+                })
+            })
+            .collect()
+    }
+
+    /// Address of the account table row written for a created account, if the block has one
+    fn created_account_row(&self, create_action: &CreateAction) -> Option<Address> {
+        self.decoded_rows.iter().rev().find_map(|row| match row {
+            DecodedRow::Account { present: true, row } if row.account == create_action.account => {
+                Some(Address::from(row.address.data))
+            }
+            _ => None,
+        })
+    }
+
+    /// Address of a created account, its account table row wins over the derived address
+    pub fn created_address(&self, create_action: &CreateAction) -> Address {
+        self.created_account_row(create_action)
+            .unwrap_or_else(|| create_action.address())
+    }
+
+    /// Created accounts whose derived address differs from the one in their account table row,
+    /// when the block carries that row
+    pub fn create_address_mismatches(&self) -> Vec<TranslatorWarning> {
+        self.new_wallets
+            .iter()
+            .filter_map(|wallet| {
+                let WalletEvents::CreateWallet(_, create_action) = wallet else {
+                    return None;
+                };
+                let table = self.created_account_row(create_action)?;
+                let derived = create_action.address();
+                if derived == table {
+                    return None;
+                }
+                Some(TranslatorWarning::CreateAddressMismatch {
+                    block_num: self.block_num,
+                    account: create_action.account,
+                    derived,
+                    table,
+                })
+            })
+            .collect()
+    }
+
+    /// Compares the gas and count of the raw transactions with the counters of the last config
+    /// row of the block, only possible when the row was updated during this block
+    pub fn config_row_mismatch(&self) -> Option<TranslatorWarning> {
+        let config = self.decoded_rows.iter().rev().find_map(|row| match row {
+            DecodedRow::Config(config) => Some(config),
+            _ => None,
+        })?;
+        if config.last_block != self.block_num {
+            return None;
+        }
+
+        let contract_gas_used = U256::from_be_slice(&config.gas_used_block.data);
+        if contract_gas_used == U256::from(self.raw_gas_used)
+            && config.trx_index as usize == self.raw_transactions
+        {
+            return None;
+        }
+        Some(TranslatorWarning::ConfigRowMismatch {
+            block_num: self.block_num,
+            gas_used: self.raw_gas_used,
+            contract_gas_used,
+            transactions: self.raw_transactions,
+            contract_trx_index: config.trx_index,
+        })
+    }
+
     fn find_config_row(&self) -> Option<&EvmContractConfigRow> {
         return self.decoded_rows.iter().find_map(|row| {
             if let DecodedRow::Config(config) = row {
@@ -183,11 +842,20 @@ impl ProcessingEVMBlock {
         });
     }
 
+    fn push_transaction(&mut self, transaction: TelosEVMTransaction) {
+        let full_receipt = transaction.receipt(self.cumulative_gas_used);
+        self.cumulative_gas_used = full_receipt.receipt.cumulative_gas_used;
+        self.transactions.push((transaction, full_receipt));
+    }
+
     async fn handle_action(
         &mut self,
         action: Box<dyn BasicTrace + Send>,
-        native_to_evm_cache: &NameToAddressCache,
-    ) {
+        origin: NativeOrigin,
+        chain_spec: &TelosChainSpec,
+        resolver: &impl AddressResolver,
+    ) -> Result<(), TranslationError> {
+        let rules = chain_spec.rules_at(self.block_num, self.revision);
         let action_name = action.action_name();
         let action_account = action.action_account();
         let action_receiver = action.receiver();
@@ -203,27 +871,66 @@ impl ProcessingEVMBlock {
         } else if action_account == EOSIO_EVM && action_name == RAW {
             // Normally signed EVM transaction
             let raw: RawAction = decode(&action.data());
-            let printed_receipt = PrintedReceipt::from_console(action.console());
-            if printed_receipt.is_none() {
-                panic!(
-                    "No printed receipt found for raw action in block: {}",
-                    self.block_num
-                );
+            if raw.estimate_gas {
+                // Estimations always revert on chain, only actions with a receipt get here so
+                // this one went through
+                let warning = TranslatorWarning::GasEstimation {
+                    block_num: self.block_num,
+                    raw_tx_hash: keccak256(&raw.tx),
+                };
+                warn!("{warning}");
+                self.warnings.push(warning);
+                return Ok(());
+            }
+            let printed_receipt =
+                PrintedReceipt::from_console(action.console()).map_err(|error| {
+                    TranslationError::PrintedReceipt {
+                        block_num: self.block_num,
+                        error,
+                    }
+                })?;
+            if printed_receipt.trx_index as usize != self.raw_transactions {
+                let warning = TranslatorWarning::TrxIndexMismatch {
+                    block_num: self.block_num,
+                    printed: printed_receipt.trx_index,
+                    translated: self.raw_transactions,
+                };
+                warn!("{warning}");
+                self.warnings.push(warning);
             }
+            let origin = NativeOrigin {
+                ram_payer: Some(raw.ram_payer),
+                ..origin
+            };
             let transaction_result = TelosEVMTransaction::from_raw_action(
                 self.chain_id,
                 self.transactions.len(),
                 self.block_hash,
                 raw,
-                printed_receipt.unwrap(),
+                printed_receipt,
+                origin,
+                &rules,
             )
             .await;
 
             match transaction_result {
                 Ok(transaction) => {
-                    let full_receipt = transaction.receipt(self.cumulative_gas_used);
-                    self.cumulative_gas_used = full_receipt.receipt.cumulative_gas_used;
-                    self.transactions.push((transaction, full_receipt));
+                    if let Some(printed) = transaction.created_address {
+                        let expected = transaction.expected_created_address();
+                        if expected != Some(printed) {
+                            let warning = TranslatorWarning::PrintedCreatedAddressMismatch {
+                                block_num: self.block_num,
+                                tx_hash: *transaction.hash(),
+                                printed,
+                                expected,
+                            };
+                            warn!("{warning}");
+                            self.warnings.push(warning);
+                        }
+                    }
+                    self.raw_transactions += 1;
+                    self.raw_gas_used += transaction.receipt.gas_used;
+                    self.push_transaction(transaction)
                 }
                 Err(e) => {
                     panic!("Error handling action. Error: {}", e);
@@ -236,13 +943,14 @@ impl ProcessingEVMBlock {
                 self.chain_id,
                 self.transactions.len(),
                 self.block_hash,
+                self.block_num,
                 withdraw_action,
-                native_to_evm_cache,
+                origin,
+                &rules,
+                resolver,
             )
-            .await;
-            let full_receipt = transaction.receipt(self.cumulative_gas_used);
-            self.cumulative_gas_used = full_receipt.receipt.cumulative_gas_used;
-            self.transactions.push((transaction, full_receipt));
+            .await?;
+            self.push_transaction(transaction);
         } else if action_account == EOSIO_TOKEN
             && action_name == TRANSFER
             && action_receiver == EOSIO_EVM
@@ -252,20 +960,30 @@ impl ProcessingEVMBlock {
             if transfer_action.to.n != EOSIO_EVM
                 || SYSTEM_ACCOUNTS.contains(&transfer_action.from.n)
             {
-                return;
+                return Ok(());
+            }
+            if transfer_action.quantity.symbol() != Symbol::new("TLOS", 4) {
+                let warning = TranslatorWarning::UnexpectedDepositSymbol {
+                    block_num: self.block_num,
+                    from: transfer_action.from,
+                };
+                warn!("{warning}");
+                self.warnings.push(warning);
+                return Ok(());
             }
 
             let transaction = TelosEVMTransaction::from_transfer(
                 self.chain_id,
                 self.transactions.len(),
                 self.block_hash,
+                self.block_num,
                 transfer_action,
-                native_to_evm_cache,
+                origin,
+                &rules,
+                resolver,
             )
-            .await;
-            let full_receipt = transaction.receipt(self.cumulative_gas_used);
-            self.cumulative_gas_used = full_receipt.receipt.cumulative_gas_used;
-            self.transactions.push((transaction, full_receipt));
+            .await?;
+            self.push_transaction(transaction);
         } else if action_account == EOSIO_EVM && action_name == DORESOURCES {
             let config_delta_row = self
                 .find_config_row()
@@ -277,6 +995,7 @@ impl ProcessingEVMBlock {
         } else if action_account == EOSIO_EVM && action_name == SETREVISION {
             let rev_action: SetRevisionAction = decode(&action.data());
 
+            self.revision = rev_action.new_revision as u64;
             self.new_revision = Some((
                 self.transactions.len() as u64,
                 rev_action.new_revision as u64,
@@ -295,14 +1014,21 @@ impl ProcessingEVMBlock {
                 wallet_action,
             ));
         }
+        Ok(())
     }
 
+    /// `revision` is the eosio.evm revision in effect at the start of the block, the one at the
+    /// end is `new_revision` if the block changed it. Fails when the address of a deposit or
+    /// withdraw can't be resolved.
     pub async fn generate_evm_data(
         &mut self,
         parent_hash: FixedBytes<32>,
         block_delta: u32,
-        native_to_evm_cache: &NameToAddressCache,
-    ) -> (Header, ExecutionPayloadV1) {
+        revision: u64,
+        chain_spec: &TelosChainSpec,
+        resolver: &impl AddressResolver,
+    ) -> Result<(Header, ExecutionPayloadV1), TranslationError> {
+        self.revision = revision;
         if self.signed_block.is_none()
             || self.block_traces.is_none()
             || self.contract_rows.is_none()
@@ -312,7 +1038,7 @@ impl ProcessingEVMBlock {
 
         let row_deltas = self.contract_rows.clone().unwrap_or_default();
 
-        for r in row_deltas {
+        for (present, r) in row_deltas {
             match r {
                 ContractRow::V0(r) => {
                     // Global eosio.system table, since block_delta is static
@@ -327,11 +1053,16 @@ impl ProcessingEVMBlock {
                         if r.table == Name::new_from_str("config") {
                             self.decoded_rows.push(DecodedRow::Config(decode(&r.value)));
                         } else if r.table == Name::new_from_str("account") {
-                            self.decoded_rows
-                                .push(DecodedRow::Account(decode(&r.value)));
+                            self.decoded_rows.push(DecodedRow::Account {
+                                present,
+                                row: decode(&r.value),
+                            });
                         } else if r.table == Name::new_from_str("accountstate") {
-                            self.decoded_rows
-                                .push(DecodedRow::AccountState(decode(&r.value)));
+                            self.decoded_rows.push(DecodedRow::AccountState {
+                                present,
+                                account_index: r.scope.value(),
+                                row: decode(&r.value),
+                            });
                         }
                     }
                 }
@@ -339,18 +1070,71 @@ impl ProcessingEVMBlock {
         }
 
         let traces = self.block_traces.clone().unwrap_or_default();
+        let mut actions = vec![];
 
         for t in traces {
             match t {
                 TransactionTrace::V0(t) => {
+                    // Deferred transactions that failed are reported nested in the onerror trace
+                    if t.failed_dtrx_trace.is_some() {
+                        self.skipped_transactions += 1;
+                    }
+                    if t.status != transaction_status::EXECUTED || t.except.is_some() {
+                        debug!(
+                            "Skipping native transaction {} with status {} in block {}",
+                            hex::encode(t.id.data),
+                            t.status,
+                            self.block_num
+                        );
+                        self.skipped_transactions += 1;
+                        continue;
+                    }
                     for action in t.action_traces {
-                        self.handle_action(Box::new(action), native_to_evm_cache)
-                            .await;
+                        if action.receipt().is_none() || action.except().is_some() {
+                            self.skipped_actions += 1;
+                            continue;
+                        }
+                        actions.push((t.id, action));
                     }
                 }
             }
         }
 
+        // Traces are ordered by action ordinal, inline actions and notifications are not
+        // necessarily in execution order, the global sequence is
+        actions.sort_by_key(|(_trx_id, action)| action.global_sequence());
+
+        // Most lookups done while handling the actions are for accounts this block touches
+        resolver.record_block(self.block_num, &self.decoded_rows);
+        for (_trx_id, action) in &actions {
+            if action.action_account() == EOSIO_EVM && action.action_name() == OPENWALLET {
+                let wallet_action: OpenWalletAction = decode(&action.data());
+                resolver.record_wallet(
+                    wallet_action.account.value(),
+                    Address::from(wallet_action.address.data),
+                );
+            }
+        }
+
+        for (trx_id, action) in actions {
+            let origin = NativeOrigin {
+                trx_id: B256::from(trx_id.data),
+                action_ordinal: action.action_ordinal(),
+                global_sequence: action.global_sequence(),
+                authorization: action.authorization(),
+                ram_payer: None,
+            };
+            self.handle_action(Box::new(action), origin, chain_spec, resolver)
+                .await?;
+        }
+
+        if self.skipped_transactions > 0 || self.skipped_actions > 0 {
+            warn!(
+                "Skipped {} failed native transactions and {} failed actions in block {}",
+                self.skipped_transactions, self.skipped_actions, self.block_num
+            );
+        }
+
         let tx_root_hash =
             ordered_trie_root_with_encoder(&self.transactions, |(tx, _receipt), buf| {
                 match &tx.envelope {
@@ -374,6 +1158,7 @@ impl ProcessingEVMBlock {
             logs_bloom.accrue_bloom(&receipt.bloom);
         }
 
+        let rules = chain_spec.rules_at(self.block_num, self.revision);
         let header = Header {
             parent_hash,
             ommers_hash: EMPTY_OMMER_ROOT_HASH,
@@ -385,7 +1170,7 @@ impl ProcessingEVMBlock {
             logs_bloom,
             difficulty: Default::default(),
             number: (self.block_num - block_delta) as u64,
-            gas_limit: 0x7fffffff,
+            gas_limit: rules.gas_limit as u128,
             gas_used: self.cumulative_gas_used as u128,
             timestamp: (((self.signed_block.clone().unwrap().header.header.timestamp as u64)
                 * ANTELOPE_INTERVAL_MS)
@@ -404,8 +1189,8 @@ impl ProcessingEVMBlock {
         let base_fee_per_gas = U256::from(
             header
                 .base_fee_per_gas
-                .filter(|&fee| fee > MINIMUM_FEE_PER_GAS)
-                .unwrap_or(MINIMUM_FEE_PER_GAS),
+                .filter(|&fee| fee > rules.minimum_fee_per_gas)
+                .unwrap_or(rules.minimum_fee_per_gas),
         );
 
         let transactions = self
@@ -431,7 +1216,7 @@ impl ProcessingEVMBlock {
             transactions,
         };
 
-        (header, exec_payload)
+        Ok((header, exec_payload))
     }
 }
 
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+/// Rules the translator applies to a block, changing across the network history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelosRules {
+    /// Pad the `s` value of translator generated signatures with zeros instead of 0xff
+    pub bad_sig_padding: bool,
+    pub minimum_fee_per_gas: u128,
+    pub gas_limit: u64,
+    /// EIP-2718 type ids accepted in eosio.evm::raw actions, 0 being legacy
+    pub allowed_tx_types: Vec<u8>,
+}
+
+impl Default for TelosRules {
+    fn default() -> Self {
+        TelosRules {
+            bad_sig_padding: false,
+            minimum_fee_per_gas: 7,
+            gas_limit: 0x7fffffff,
+            allowed_tx_types: vec![0, 2],
+        }
+    }
+}
+
+impl TelosRules {
+    pub fn allows_tx_type(&self, tx_type: u8) -> bool {
+        self.allowed_tx_types.contains(&tx_type)
+    }
+}
+
+/// Rules a hardfork changes, the ones left out keep their earlier value
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelosRulesOverride {
+    pub bad_sig_padding: Option<bool>,
+    pub minimum_fee_per_gas: Option<u128>,
+    pub gas_limit: Option<u64>,
+    pub allowed_tx_types: Option<Vec<u8>>,
+}
+
+impl TelosRulesOverride {
+    pub fn apply(&self, rules: &mut TelosRules) {
+        if let Some(bad_sig_padding) = self.bad_sig_padding {
+            rules.bad_sig_padding = bad_sig_padding;
+        }
+        if let Some(minimum_fee_per_gas) = self.minimum_fee_per_gas {
+            rules.minimum_fee_per_gas = minimum_fee_per_gas;
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            rules.gas_limit = gas_limit;
+        }
+        if let Some(allowed_tx_types) = &self.allowed_tx_types {
+            rules.allowed_tx_types = allowed_tx_types.clone();
+        }
+    }
+}
+
+/// When a hardfork starts to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HardforkCondition {
+    /// Native block number, inclusive
+    Block(u32),
+    /// eosio.evm revision set through the setrevision action
+    Revision(u64),
+}
+
+impl HardforkCondition {
+    pub fn is_active(&self, block_num: u32, revision: u64) -> bool {
+        match self {
+            HardforkCondition::Block(block) => block_num >= *block,
+            HardforkCondition::Revision(min_revision) => revision >= *min_revision,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelosHardfork {
+    pub name: String,
+    pub activation: HardforkCondition,
+    pub rules: TelosRulesOverride,
+}
+
+/// Per network rule schedule, so a single binary can translate a chain from genesis to head.
+/// The default spec has no hardforks, its rules are those of builds without the former
+/// `bad_sig_padding` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelosChainSpec {
+    /// Rules in effect before any hardfork
+    pub base: TelosRules,
+    /// In activation order, active hardforks are applied over the base rules one after the
+    /// other
+    pub hardforks: Vec<TelosHardfork>,
+}
+
+impl TelosChainSpec {
+    pub fn rules_at(&self, block_num: u32, revision: u64) -> TelosRules {
+        let mut rules = self.base.clone();
+        for hardfork in &self.hardforks {
+            if hardfork.activation.is_active(block_num, revision) {
+                hardfork.rules.apply(&mut rules);
+            }
+        }
+        rules
+    }
+}
@@ -0,0 +1,151 @@
+use crate::block::TranslatorWarning;
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Bits of the bloom filter per expected transaction and hash functions, about 1% false
+/// positives when full
+const BITS_PER_TRANSACTION: usize = 10;
+const BLOOM_HASHES: u64 = 7;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplicateHashConfig {
+    /// Native blocks a transaction hash is remembered for
+    pub window_blocks: u32,
+    /// Transactions expected over the window, sizes the bloom filter
+    pub expected_transactions: usize,
+}
+
+impl Default for DuplicateHashConfig {
+    fn default() -> Self {
+        DuplicateHashConfig {
+            window_blocks: 100_000,
+            expected_transactions: 1_000_000,
+        }
+    }
+}
+
+/// Where a transaction was translated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxPosition {
+    pub block_num: u32,
+    pub tx_index: usize,
+}
+
+struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    fn new(expected_transactions: usize) -> Self {
+        let words = (expected_transactions.max(1) * BITS_PER_TRANSACTION).div_ceil(64);
+        BloomFilter {
+            bits: vec![0; words],
+        }
+    }
+
+    /// Transaction hashes are keccak256 outputs, two of their words are as good as two
+    /// independent hashes for double hashing
+    fn positions(&self, hash: &B256) -> impl Iterator<Item = usize> {
+        let h1 = u64::from_be_bytes(hash[..8].try_into().unwrap());
+        let h2 = u64::from_be_bytes(hash[8..16].try_into().unwrap()) | 1;
+        let len = self.bits.len() as u64 * 64;
+        (0..BLOOM_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    fn insert(&mut self, hash: &B256) {
+        for position in self.positions(hash) {
+            self.bits[position / 64] |= 1 << (position % 64);
+        }
+    }
+
+    fn contains(&self, hash: &B256) -> bool {
+        self.positions(hash)
+            .all(|position| self.bits[position / 64] & (1 << (position % 64)) != 0)
+    }
+
+    fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+    }
+}
+
+/// Remembers the transaction hashes of the last `window_blocks` native blocks. The bloom
+/// filter answers most lookups, only its hits are checked against the exact store.
+pub struct DuplicateHashDetector {
+    window_blocks: u32,
+    bloom: BloomFilter,
+    seen: HashMap<B256, TxPosition>,
+    blocks: VecDeque<(u32, Vec<B256>)>,
+    /// Hashes evicted from the exact store but still set in the bloom filter
+    stale: usize,
+}
+
+impl DuplicateHashDetector {
+    pub fn new(config: &DuplicateHashConfig) -> Self {
+        DuplicateHashDetector {
+            window_blocks: config.window_blocks,
+            bloom: BloomFilter::new(config.expected_transactions),
+            seen: HashMap::new(),
+            blocks: VecDeque::new(),
+            stale: 0,
+        }
+    }
+
+    /// Records the transaction hashes of a block, in block order, and returns one warning per
+    /// hash already seen in the window, including earlier in the same block
+    pub fn check_block(
+        &mut self,
+        block_num: u32,
+        hashes: impl IntoIterator<Item = B256>,
+    ) -> Vec<TranslatorWarning> {
+        self.evict(block_num);
+
+        let mut duplicates = vec![];
+        let mut block_hashes = vec![];
+        for (tx_index, tx_hash) in hashes.into_iter().enumerate() {
+            let position = TxPosition {
+                block_num,
+                tx_index,
+            };
+            if self.bloom.contains(&tx_hash) {
+                if let Some(first) = self.seen.get(&tx_hash) {
+                    duplicates.push(TranslatorWarning::DuplicateTxHash {
+                        tx_hash,
+                        first: *first,
+                        duplicate: position,
+                    });
+                    continue;
+                }
+            }
+            self.bloom.insert(&tx_hash);
+            self.seen.insert(tx_hash, position);
+            block_hashes.push(tx_hash);
+        }
+        self.blocks.push_back((block_num, block_hashes));
+        duplicates
+    }
+
+    fn evict(&mut self, block_num: u32) {
+        while let Some((oldest, _)) = self.blocks.front() {
+            if oldest.saturating_add(self.window_blocks) > block_num {
+                break;
+            }
+            let (_, hashes) = self.blocks.pop_front().unwrap();
+            for hash in hashes {
+                self.seen.remove(&hash);
+                self.stale += 1;
+            }
+        }
+
+        // Bits of evicted hashes only ever add false positives, rebuild once they outnumber
+        // the live ones
+        if self.stale > self.seen.len() {
+            self.bloom.clear();
+            for hash in self.seen.keys() {
+                self.bloom.insert(hash);
+            }
+            self.stale = 0;
+        }
+    }
+}
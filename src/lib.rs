@@ -1,5 +1,13 @@
+pub mod account_history;
+pub mod address_store;
 pub mod block;
+pub mod chain_spec;
+pub mod duplicate_hashes;
+pub mod log_writer;
+pub mod resolver;
 pub mod rlp;
+pub mod snapshot;
+pub mod state;
 pub mod tasks;
 pub mod transaction;
 pub mod translator;
@@ -0,0 +1,85 @@
+use eyre::{eyre, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+#[derive(Debug)]
+enum Message {
+    Line(Vec<u8>),
+    Flush,
+}
+
+/// Appends JSON lines to a file from a thread of its own, so translating never waits on the
+/// disk. The first write error stops the writer and is returned by `check` from then on.
+/// Dropping the writer waits for the queued lines to be written.
+#[derive(Debug)]
+pub struct LogWriter {
+    lines: Option<Sender<Message>>,
+    error: Arc<Mutex<Option<String>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LogWriter {
+    pub fn new(file: File) -> Self {
+        let (lines, rx) = channel();
+        let error = Arc::new(Mutex::new(None));
+        let thread_error = error.clone();
+        let thread = thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+            let result = rx
+                .into_iter()
+                .try_for_each(|message| match message {
+                    Message::Line(line) => writer.write_all(&line),
+                    Message::Flush => writer.flush(),
+                })
+                .and_then(|()| writer.flush());
+            if let Err(error) = result {
+                *thread_error.lock().unwrap() = Some(error.to_string());
+            }
+        });
+        LogWriter {
+            lines: Some(lines),
+            error,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues `entry` as one line
+    pub fn append(&self, entry: &impl Serialize) {
+        let mut line = serde_json::to_vec(entry).expect("Log entries always serialize");
+        line.push(b'\n');
+        self.send(Message::Line(line));
+    }
+
+    /// Writes the queued lines out to the file
+    pub fn flush(&self) {
+        self.send(Message::Flush);
+    }
+
+    /// Fails once a write failed
+    pub fn check(&self) -> Result<()> {
+        match &*self.error.lock().unwrap() {
+            Some(error) => Err(eyre!("Failed to write log: {error}")),
+            None => Ok(()),
+        }
+    }
+
+    /// After a failure the writer thread is gone and messages are dropped, `check` reports it
+    fn send(&self, message: Message) {
+        if let Some(lines) = &self.lines {
+            let _ = lines.send(message);
+        }
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        self.lines.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use antelope::api::client::{APIClient, DefaultProvider};
 use clap::Parser;
 use std::fs;
+use telos_translator_rs::snapshot::{EvmSnapshot, DEFAULT_PAGE_SIZE};
 use telos_translator_rs::translator::{Translator, TranslatorConfig};
 use tokio::sync::mpsc;
 use tracing::error;
@@ -9,6 +11,17 @@ use tracing::error;
 struct Args {
     #[arg(long, default_value = "config.toml")]
     config: String,
+
+    /// Write a snapshot of the eosio.evm tables at the http_endpoint head to this path and exit
+    #[arg(long)]
+    snapshot: Option<String>,
+
+    /// Write the eosio.evm tables at the http_endpoint head as an EVM genesis alloc and exit
+    #[arg(long)]
+    genesis_alloc: Option<String>,
+
+    #[arg(long, default_value_t = DEFAULT_PAGE_SIZE)]
+    snapshot_page_size: u32,
 }
 
 #[tokio::main]
@@ -20,6 +33,28 @@ async fn main() {
     let config_contents = fs::read_to_string(args.config).expect("Could not read config file");
     let config: TranslatorConfig =
         toml::from_str(&config_contents).expect("Could not parse config as toml");
+
+    if args.snapshot.is_some() || args.genesis_alloc.is_some() {
+        let api_client = APIClient::<DefaultProvider>::default_provider(config.http_endpoint)
+            .expect("Failed to create API client");
+        let snapshot = match EvmSnapshot::fetch(&api_client, args.snapshot_page_size).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                error!("Failed to take snapshot: {e:?}");
+                return;
+            }
+        };
+        if let Some(path) = args.snapshot {
+            snapshot.write(path).expect("Could not write snapshot");
+        }
+        if let Some(path) = args.genesis_alloc {
+            snapshot
+                .write_genesis_alloc(path)
+                .expect("Could not write genesis alloc");
+        }
+        return;
+    }
+
     let (stop_tx, stop_rx) = mpsc::channel::<()>(1);
 
     if let Err(e) = Translator::new(config).launch(None, stop_tx, stop_rx).await {
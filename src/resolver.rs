@@ -0,0 +1,487 @@
+use crate::block::DecodedRow;
+use crate::snapshot::EvmSnapshot;
+use crate::types::evm_types::AccountRow;
+use crate::types::names::EOSIO_EVM;
+use alloy::primitives::Address;
+use antelope::api::client::{APIClient, DefaultProvider};
+use antelope::api::v1::structs::{GetTableRowsParams, IndexPosition, TableIndexType};
+use antelope::chain::name::Name;
+use eyre::{eyre, Result};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+use tracing::{info, warn};
+
+/// A lookup that could not be answered, never to be taken as the account not existing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// Every endpoint failed or timed out on every attempt
+    Unavailable { attempts: u32, last_error: String },
+    /// A block refers to an account index without an eosio.evm account row
+    UnknownIndex { index: u64, block_num: u32 },
+    /// A deposit or withdraw names an account without a linked EVM address
+    UnknownName { name: u64, block_num: u32 },
+    /// A snapshot resolver was asked for a block at or before its snapshot
+    BeforeSnapshot { block_num: u32, snapshot_block: u32 },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Unavailable {
+                attempts,
+                last_error,
+            } => write!(
+                f,
+                "Address lookup failed after {attempts} attempts, last error: {last_error}"
+            ),
+            ResolveError::UnknownIndex { index, block_num } => {
+                write!(f, "No account with index {index} in block {block_num}")
+            }
+            ResolveError::UnknownName { name, block_num } => write!(
+                f,
+                "No address linked to account {} in block {block_num}",
+                Name::from_u64(*name)
+            ),
+            ResolveError::BeforeSnapshot {
+                block_num,
+                snapshot_block,
+            } => write!(
+                f,
+                "Block {block_num} is not after the snapshot taken at block {snapshot_block}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// Resolves the EVM address linked to a native account, by account name or by the index of its
+/// eosio.evm account table row. `Ok(None)` means the account doesn't exist.
+pub trait AddressResolver: Send + Sync {
+    /// Address of the native account `name` as of the native block `block_num`
+    fn address_by_name(
+        &self,
+        name: u64,
+        block_num: u32,
+    ) -> impl Future<Output = Result<Option<Address>, ResolveError>> + Send;
+
+    /// Address of the account table row `index` as of the native block `block_num`
+    fn address_by_index(
+        &self,
+        index: u64,
+        block_num: u32,
+    ) -> impl Future<Output = Result<Option<Address>, ResolveError>> + Send;
+
+    /// Addresses of the accounts `names` that exist as of `block_num`, several lookups at once
+    fn addresses_by_names(
+        &self,
+        names: &[u64],
+        block_num: u32,
+    ) -> impl Future<Output = Result<HashMap<u64, Address>, ResolveError>> + Send {
+        async move {
+            let mut addresses = HashMap::new();
+            for &name in names {
+                if let Some(address) = self.address_by_name(name, block_num).await? {
+                    addresses.insert(name, address);
+                }
+            }
+            Ok(addresses)
+        }
+    }
+
+    /// Addresses of the account table rows `indexes` that exist as of `block_num`, several
+    /// lookups at once
+    fn addresses_by_indexes(
+        &self,
+        indexes: &[u64],
+        block_num: u32,
+    ) -> impl Future<Output = Result<HashMap<u64, Address>, ResolveError>> + Send {
+        async move {
+            let mut addresses = HashMap::new();
+            for &index in indexes {
+                if let Some(address) = self.address_by_index(index, block_num).await? {
+                    addresses.insert(index, address);
+                }
+            }
+            Ok(addresses)
+        }
+    }
+
+    /// Account rows of a block, fed before its actions are resolved
+    fn record_block(&self, _block_num: u32, _rows: &[DecodedRow]) {}
+
+    /// Name linked to an address by an openwallet action of the block being translated
+    fn record_wallet(&self, _name: u64, _address: Address) {}
+}
+
+/// Timeout and retries of HTTP lookups. Every attempt tries all endpoints once, starting with
+/// the last one that answered, attempts are separated by an exponential backoff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpRetryConfig {
+    pub timeout_ms: u64,
+    pub attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for HttpRetryConfig {
+    fn default() -> Self {
+        HttpRetryConfig {
+            timeout_ms: 5_000,
+            attempts: 5,
+            initial_backoff_ms: 250,
+            max_backoff_ms: 10_000,
+        }
+    }
+}
+
+/// Concurrent requests of a batch lookup
+const BATCH_CONCURRENCY: usize = 16;
+/// Indexes closer than this are fetched with a single ranged query, the rows in between are
+/// fetched for nothing
+const MAX_INDEX_GAP: u64 = 8;
+/// Most rows asked for by a ranged query
+const MAX_RANGE_ROWS: u64 = 500;
+
+/// Splits sorted, deduplicated indexes into the inclusive ranges of the ranged queries
+fn index_ranges(indexes: &[u64]) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = vec![];
+    for &index in indexes {
+        match ranges.last_mut() {
+            Some((lower, upper))
+                if index - *upper <= MAX_INDEX_GAP && index - *lower < MAX_RANGE_ROWS =>
+            {
+                *upper = index
+            }
+            _ => ranges.push((index, index)),
+        }
+    }
+    ranges
+}
+
+/// Queries the current eosio.evm account table of a node, failing over between endpoints.
+/// Tables are only readable at the node head, so `block_num` is ignored: accounts created
+/// after the block are found and accounts removed since are not. Put the account history in
+/// front of it to answer as of the block.
+pub struct HttpResolver {
+    endpoints: Vec<(String, APIClient<DefaultProvider>)>,
+    retry: HttpRetryConfig,
+    preferred: AtomicUsize,
+}
+
+impl HttpResolver {
+    pub fn new(api_client: APIClient<DefaultProvider>) -> Self {
+        HttpResolver {
+            endpoints: vec![(String::from("default"), api_client)],
+            retry: HttpRetryConfig::default(),
+            preferred: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_endpoints(endpoints: &[String], retry: HttpRetryConfig) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err(eyre!("At least one HTTP endpoint is needed"));
+        }
+        let endpoints = endpoints
+            .iter()
+            .map(|endpoint| {
+                APIClient::<DefaultProvider>::default_provider(endpoint.clone())
+                    .map(|client| (endpoint.clone(), client))
+                    .map_err(|error| eyre!("Failed to create API client for {endpoint}: {error}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(HttpResolver {
+            endpoints,
+            retry,
+            preferred: AtomicUsize::new(0),
+        })
+    }
+
+    async fn account_row(
+        &self,
+        key: u64,
+        by_name: bool,
+    ) -> Result<Option<AccountRow>, ResolveError> {
+        Ok(self
+            .account_rows(key, key, by_name)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    /// Rows with a primary or account name key between `lower` and `upper`, both inclusive
+    async fn account_rows(
+        &self,
+        lower: u64,
+        upper: u64,
+        by_name: bool,
+    ) -> Result<Vec<AccountRow>, ResolveError> {
+        let mut rows = vec![];
+        let mut lower_bound = lower;
+        loop {
+            let limit = (upper - lower_bound + 1).min(MAX_RANGE_ROWS) as u32;
+            let (page, more) = self.table_rows(lower_bound, upper, limit, by_name).await?;
+            let last_key = page.last().map(|row| {
+                if by_name {
+                    row.account.value()
+                } else {
+                    row.index
+                }
+            });
+            rows.extend(page);
+            match last_key {
+                Some(last_key) if more && last_key < upper => lower_bound = last_key + 1,
+                _ => break,
+            }
+        }
+        Ok(rows)
+    }
+
+    /// One page of rows and whether more rows are left in the range
+    async fn table_rows(
+        &self,
+        lower: u64,
+        upper: u64,
+        limit: u32,
+        by_name: bool,
+    ) -> Result<(Vec<AccountRow>, bool), ResolveError> {
+        let evm_contract = Name::from_u64(EOSIO_EVM);
+        // TODO: hardcode this in names.rs for performance
+        let account = Name::new_from_str("account");
+        let params = || GetTableRowsParams {
+            code: evm_contract,
+            table: account,
+            scope: Some(evm_contract),
+            lower_bound: Some(TableIndexType::UINT64(lower)),
+            upper_bound: Some(TableIndexType::UINT64(upper)),
+            limit: Some(limit),
+            reverse: None,
+            index_position: Some(if by_name {
+                IndexPosition::TERTIARY
+            } else {
+                IndexPosition::PRIMARY
+            }),
+            show_payer: None,
+        };
+
+        let mut backoff = Duration::from_millis(self.retry.initial_backoff_ms);
+        let attempts = self.retry.attempts.max(1);
+        let mut last_error = String::new();
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_millis(self.retry.max_backoff_ms));
+            }
+            let first = self.preferred.load(Ordering::Relaxed);
+            for offset in 0..self.endpoints.len() {
+                let position = (first + offset) % self.endpoints.len();
+                let (endpoint, client) = &self.endpoints[position];
+                let request = client.v1_chain.get_table_rows::<AccountRow>(params());
+                match timeout(Duration::from_millis(self.retry.timeout_ms), request).await {
+                    Ok(Ok(result)) => {
+                        self.preferred.store(position, Ordering::Relaxed);
+                        return Ok((result.rows, result.more));
+                    }
+                    Ok(Err(error)) => last_error = format!("{endpoint}: {error:?}"),
+                    Err(_) => last_error = format!("{endpoint}: timed out"),
+                }
+                warn!("Account lookup failed, {last_error}");
+            }
+        }
+        Err(ResolveError::Unavailable {
+            attempts,
+            last_error,
+        })
+    }
+}
+
+impl AddressResolver for HttpResolver {
+    async fn address_by_name(
+        &self,
+        name: u64,
+        _block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        let row = self.account_row(name, true).await?;
+        if row.is_none() {
+            info!("Got empty rows for {}", Name::from_u64(name).as_string());
+        }
+        Ok(row.map(|row| Address::from(row.address.data)))
+    }
+
+    async fn address_by_index(
+        &self,
+        index: u64,
+        _block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        let row = self.account_row(index, false).await?;
+        if row.is_none() {
+            info!("Got empty rows for {}", index);
+        }
+        Ok(row.map(|row| Address::from(row.address.data)))
+    }
+
+    /// One request per name, names are too sparse for ranged queries
+    async fn addresses_by_names(
+        &self,
+        names: &[u64],
+        _block_num: u32,
+    ) -> Result<HashMap<u64, Address>, ResolveError> {
+        let rows = stream::iter(names)
+            .map(|&name| self.account_row(name, true))
+            .buffer_unordered(BATCH_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(rows
+            .into_iter()
+            .flatten()
+            .map(|row| (row.account.value(), Address::from(row.address.data)))
+            .collect())
+    }
+
+    /// Ranged queries over the primary key, indexes close to each other share a request
+    async fn addresses_by_indexes(
+        &self,
+        indexes: &[u64],
+        _block_num: u32,
+    ) -> Result<HashMap<u64, Address>, ResolveError> {
+        let mut sorted = indexes.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let pages = stream::iter(index_ranges(&sorted))
+            .map(|(lower, upper)| self.account_rows(lower, upper, false))
+            .buffer_unordered(BATCH_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(pages
+            .into_iter()
+            .flatten()
+            .filter(|row| sorted.binary_search(&row.index).is_ok())
+            .map(|row| (row.index, Address::from(row.address.data)))
+            .collect())
+    }
+}
+
+/// Plain maps, kept up to date with the account rows and wallet events it is fed
+#[derive(Debug, Default)]
+pub struct MemoryResolver {
+    names: RwLock<HashMap<u64, Address>>,
+    indexes: RwLock<HashMap<u64, Address>>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `name` is 0 for accounts created from the EVM side
+    pub fn insert(&self, name: u64, index: u64, address: Address) {
+        if name != 0 {
+            self.names.write().unwrap().insert(name, address);
+        }
+        self.indexes.write().unwrap().insert(index, address);
+    }
+}
+
+impl AddressResolver for MemoryResolver {
+    async fn address_by_name(
+        &self,
+        name: u64,
+        _block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        Ok(self.names.read().unwrap().get(&name).copied())
+    }
+
+    async fn address_by_index(
+        &self,
+        index: u64,
+        _block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        Ok(self.indexes.read().unwrap().get(&index).copied())
+    }
+
+    fn record_block(&self, _block_num: u32, rows: &[DecodedRow]) {
+        for row in rows {
+            if let DecodedRow::Account { present, row } = row {
+                if *present {
+                    self.insert(
+                        row.account.value(),
+                        row.index,
+                        Address::from(row.address.data),
+                    );
+                } else {
+                    self.names.write().unwrap().remove(&row.account.value());
+                    self.indexes.write().unwrap().remove(&row.index);
+                }
+            }
+        }
+    }
+
+    fn record_wallet(&self, name: u64, address: Address) {
+        self.names.write().unwrap().insert(name, address);
+    }
+}
+
+/// Accounts of an eosio.evm table snapshot, only answers for blocks after the snapshot and
+/// fails for the others. Recorded blocks are ignored, so rows removed after the snapshot still
+/// resolve, put the caches in front of it to follow the translated blocks.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotResolver {
+    block_num: u32,
+    names: HashMap<u64, Address>,
+    indexes: HashMap<u64, Address>,
+}
+
+impl SnapshotResolver {
+    pub fn new(snapshot: &EvmSnapshot) -> Self {
+        let mut resolver = SnapshotResolver {
+            block_num: snapshot.block_num,
+            ..Default::default()
+        };
+        for account in &snapshot.accounts {
+            let name = Name::new_from_str(&account.account).value();
+            if name != 0 {
+                resolver.names.insert(name, account.address);
+            }
+            resolver.indexes.insert(account.index, account.address);
+        }
+        resolver
+    }
+
+    fn check_block(&self, block_num: u32) -> Result<(), ResolveError> {
+        if block_num <= self.block_num {
+            return Err(ResolveError::BeforeSnapshot {
+                block_num,
+                snapshot_block: self.block_num,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl AddressResolver for SnapshotResolver {
+    async fn address_by_name(
+        &self,
+        name: u64,
+        block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        self.check_block(block_num)?;
+        Ok(self.names.get(&name).copied())
+    }
+
+    async fn address_by_index(
+        &self,
+        index: u64,
+        block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        self.check_block(block_num)?;
+        Ok(self.indexes.get(&index).copied())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::state::EvmState;
+use crate::types::evm_types::{AccountRow, AccountStateRow};
+use crate::types::names::EOSIO_EVM;
+use alloy::primitives::{Address, Bytes, B256, U256};
+use antelope::api::client::{APIClient, DefaultProvider};
+use antelope::api::v1::structs::{GetTableRowsParams, IndexPosition, TableIndexType};
+use antelope::chain::checksum::{Checksum160, Checksum256};
+use antelope::chain::name::Name;
+use antelope::chain::Packer;
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotAccount {
+    pub index: u64,
+    pub address: Address,
+    /// Linked native account, empty for accounts created from the EVM side
+    pub account: String,
+    pub nonce: u64,
+    pub balance: U256,
+    pub code: Bytes,
+    pub storage: BTreeMap<B256, U256>,
+}
+
+/// Contents of the eosio.evm account and accountstate tables at a given native block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvmSnapshot {
+    pub block_num: u32,
+    pub block_id: B256,
+    pub accounts: Vec<SnapshotAccount>,
+}
+
+/// Account entry of an EVM genesis `alloc`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisAllocAccount {
+    pub nonce: U256,
+    pub balance: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<B256, B256>,
+}
+
+impl EvmSnapshot {
+    /// Pages through the eosio.evm tables of the node. get_table_rows has no way to pin a block,
+    /// every page is read at whatever the head is when it is served, so pages of a moving head
+    /// mix states of different blocks. The read only succeeds when the head is the same before
+    /// and after, use a node that is stopped from syncing at the snapshot block.
+    pub async fn fetch(api_client: &APIClient<DefaultProvider>, page_size: u32) -> Result<Self> {
+        let info = api_client
+            .v1_chain
+            .get_info()
+            .await
+            .map_err(|error| eyre!("Failed to get chain info: {error:?}"))?;
+
+        let mut accounts = vec![];
+        for row in fetch_table::<AccountRow>(api_client, "account", EOSIO_EVM, page_size).await? {
+            let storage =
+                fetch_table::<AccountStateRow>(api_client, "accountstate", row.index, page_size)
+                    .await?
+                    .into_iter()
+                    .map(|slot| {
+                        (
+                            B256::from(slot.key.data),
+                            U256::from_be_slice(&slot.value.data),
+                        )
+                    })
+                    .filter(|(_key, value)| !value.is_zero())
+                    .collect();
+
+            accounts.push(SnapshotAccount {
+                index: row.index,
+                address: Address::from(row.address.data),
+                account: row.account.to_string(),
+                nonce: row.nonce,
+                balance: U256::from_be_slice(&row.balance.data),
+                code: Bytes::from(row.code),
+                storage,
+            });
+        }
+
+        let head = api_client
+            .v1_chain
+            .get_info()
+            .await
+            .map_err(|error| eyre!("Failed to get chain info: {error:?}"))?;
+        if head.head_block_num != info.head_block_num {
+            return Err(eyre!(
+                "Head moved from block {} to {} while reading the tables",
+                info.head_block_num,
+                head.head_block_num
+            ));
+        }
+
+        info!(
+            "Snapshot of {} accounts at block {}",
+            accounts.len(),
+            info.head_block_num
+        );
+        Ok(EvmSnapshot {
+            block_num: info.head_block_num,
+            block_id: B256::from(info.head_block_id.data),
+            accounts,
+        })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn to_genesis_alloc(&self) -> BTreeMap<Address, GenesisAllocAccount> {
+        self.accounts
+            .iter()
+            .map(|account| {
+                (
+                    account.address,
+                    GenesisAllocAccount {
+                        nonce: U256::from(account.nonce),
+                        balance: account.balance,
+                        code: (!account.code.is_empty()).then(|| account.code.clone()),
+                        storage: account
+                            .storage
+                            .iter()
+                            .map(|(key, value)| (*key, B256::from(value.to_be_bytes::<32>())))
+                            .collect(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn write_genesis_alloc(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(
+            path,
+            serde_json::to_string_pretty(&self.to_genesis_alloc())?,
+        )?;
+        Ok(())
+    }
+
+    pub fn to_state(&self) -> EvmState {
+        let mut accounts = vec![];
+        let mut storage = vec![];
+        for account in &self.accounts {
+            accounts.push(AccountRow {
+                index: account.index,
+                address: Checksum160::from_bytes(account.address.as_slice()).unwrap(),
+                account: Name::new_from_str(&account.account),
+                nonce: account.nonce,
+                code: account.code.to_vec(),
+                balance: Checksum256::from_bytes(&account.balance.to_be_bytes::<32>()).unwrap(),
+            });
+            storage.extend(
+                account
+                    .storage
+                    .iter()
+                    .map(|(key, value)| (account.index, *key, *value)),
+            );
+        }
+        EvmState::from_rows(accounts, storage)
+    }
+}
+
+/// Reads a whole eosio.evm table scope, rows are paged by primary key
+async fn fetch_table<T: Packer + Default + PrimaryKey>(
+    api_client: &APIClient<DefaultProvider>,
+    table: &str,
+    scope: u64,
+    page_size: u32,
+) -> Result<Vec<T>> {
+    let evm_contract = Name::from_u64(EOSIO_EVM);
+    let mut rows: Vec<T> = vec![];
+    let mut lower_bound = 0;
+    loop {
+        let page = api_client
+            .v1_chain
+            .get_table_rows::<T>(GetTableRowsParams {
+                code: evm_contract,
+                table: Name::new_from_str(table),
+                scope: Some(Name::from_u64(scope)),
+                lower_bound: Some(TableIndexType::UINT64(lower_bound)),
+                upper_bound: None,
+                limit: Some(page_size),
+                reverse: None,
+                index_position: Some(IndexPosition::PRIMARY),
+                show_payer: None,
+            })
+            .await
+            .map_err(|error| eyre!("Failed to read {table} table: {error:?}"))?;
+
+        let last_key = match page.rows.last() {
+            Some(row) => row.primary_key(),
+            None => break,
+        };
+        if last_key < lower_bound {
+            return Err(eyre!(
+                "Rows of {table} table are not ordered by primary key"
+            ));
+        }
+        rows.extend(page.rows);
+        if !page.more || last_key == u64::MAX {
+            break;
+        }
+        lower_bound = last_key + 1;
+    }
+    Ok(rows)
+}
+
+trait PrimaryKey {
+    fn primary_key(&self) -> u64;
+}
+
+impl PrimaryKey for AccountRow {
+    fn primary_key(&self) -> u64 {
+        self.index
+    }
+}
+
+impl PrimaryKey for AccountStateRow {
+    fn primary_key(&self) -> u64 {
+        self.index
+    }
+}
@@ -0,0 +1,156 @@
+use crate::block::{collapse_row_deltas, DecodedRow};
+use crate::types::evm_types::AccountRow;
+use alloy::primitives::{keccak256, B256, U256};
+use alloy_consensus::constants::EMPTY_ROOT_HASH;
+use reth_primitives::Account;
+use reth_trie_common::{HashBuilder, Nibbles, TrieAccount};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// How the translator uses the state root computed from the eosio.evm tables
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateRootMode {
+    /// Headers keep the empty root and no state is tracked
+    #[default]
+    Disabled,
+    /// Compute the root and report it, headers and block hashes are not affected
+    Report,
+    /// Use the computed root in headers
+    Enabled,
+}
+
+/// EVM accounts and storage rebuilt from the eosio.evm account and accountstate tables,
+/// rows are keyed by the contract account index. Trie leaves are kept encoded and in trie
+/// order, the state root only re-encodes the accounts touched since the last call and
+/// recomputes their storage roots.
+#[derive(Debug, Clone, Default)]
+pub struct EvmState {
+    accounts: HashMap<u64, AccountRow>,
+    /// RLP encoded slot values by hashed slot key
+    storage: HashMap<u64, BTreeMap<B256, Vec<u8>>>,
+    storage_roots: HashMap<u64, B256>,
+    /// RLP encoded trie accounts by hashed address
+    account_leaves: BTreeMap<B256, Vec<u8>>,
+    /// Accounts whose row changed since the last state root
+    touched: HashSet<u64>,
+    /// Accounts whose storage changed since the last state root
+    touched_storage: HashSet<u64>,
+}
+
+impl EvmState {
+    /// State from full table contents, like a snapshot taken at a given block
+    pub fn from_rows(accounts: Vec<AccountRow>, storage: Vec<(u64, B256, U256)>) -> Self {
+        let mut state = EvmState::default();
+        for account in accounts {
+            state.touched.insert(account.index);
+            state.accounts.insert(account.index, account);
+        }
+        for (index, key, value) in storage {
+            state.set_storage(index, key, value);
+        }
+        state
+    }
+
+    /// Applies the table deltas of a block
+    pub fn apply(&mut self, rows: &[DecodedRow]) {
+        for row in collapse_row_deltas(rows) {
+            match row {
+                DecodedRow::Account { present, row } => {
+                    if present {
+                        self.touched.insert(row.index);
+                        if let Some(previous) = self.accounts.insert(row.index, row.clone()) {
+                            if previous.address.data != row.address.data {
+                                self.account_leaves
+                                    .remove(&keccak256(previous.address.data));
+                            }
+                        }
+                    } else {
+                        if let Some(removed) = self.accounts.remove(&row.index) {
+                            self.account_leaves.remove(&keccak256(removed.address.data));
+                        }
+                        self.storage.remove(&row.index);
+                        self.storage_roots.remove(&row.index);
+                        self.touched.remove(&row.index);
+                        self.touched_storage.remove(&row.index);
+                    }
+                }
+                DecodedRow::AccountState {
+                    present,
+                    account_index,
+                    row,
+                } => {
+                    let value = if present {
+                        U256::from_be_slice(&row.value.data)
+                    } else {
+                        U256::ZERO
+                    };
+                    self.set_storage(account_index, B256::from(row.key.data), value);
+                }
+                DecodedRow::Config(_) => (),
+            }
+        }
+    }
+
+    fn set_storage(&mut self, index: u64, key: B256, value: U256) {
+        let slots = self.storage.entry(index).or_default();
+        if value.is_zero() {
+            slots.remove(&keccak256(key));
+        } else {
+            slots.insert(keccak256(key), alloy_rlp::encode(value));
+        }
+        self.touched.insert(index);
+        self.touched_storage.insert(index);
+    }
+
+    pub fn account_count(&self) -> usize {
+        self.accounts.len()
+    }
+
+    /// Merkle-Patricia root of the current state
+    pub fn state_root(&mut self) -> B256 {
+        for index in std::mem::take(&mut self.touched_storage) {
+            match self.storage.get(&index) {
+                Some(slots) if !slots.is_empty() => {
+                    self.storage_roots.insert(index, trie_root(slots));
+                }
+                _ => {
+                    self.storage.remove(&index);
+                    self.storage_roots.remove(&index);
+                }
+            }
+        }
+        for index in std::mem::take(&mut self.touched) {
+            // Storage written before its account row exists only counts once the row does
+            let Some(row) = self.accounts.get(&index) else {
+                continue;
+            };
+            let storage_root = self
+                .storage_roots
+                .get(&index)
+                .copied()
+                .unwrap_or(EMPTY_ROOT_HASH);
+            let account = TrieAccount::from((account(row), storage_root));
+            self.account_leaves
+                .insert(keccak256(row.address.data), alloy_rlp::encode(account));
+        }
+        trie_root(&self.account_leaves)
+    }
+}
+
+/// Root of a trie from its encoded leaves, keyed by their hashed path
+fn trie_root(leaves: &BTreeMap<B256, Vec<u8>>) -> B256 {
+    let mut hash_builder = HashBuilder::default();
+    for (key, value) in leaves {
+        hash_builder.add_leaf(Nibbles::unpack(key), value);
+    }
+    hash_builder.root()
+}
+
+fn account(row: &AccountRow) -> Account {
+    Account {
+        nonce: row.nonce,
+        balance: U256::from_be_slice(&row.balance.data),
+        bytecode_hash: (!row.code.is_empty()).then(|| keccak256(&row.code)),
+    }
+}
@@ -1,21 +1,62 @@
-use crate::block::ProcessingEVMBlock;
+use crate::block::{AddressLookups, ProcessingEVMBlock};
+use crate::resolver::AddressResolver;
+use crate::types::translator_types::NameToAddressCache;
 use eyre::Result;
+use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-pub async fn evm_block_processor(
+/// Deserializes the blocks and, unless `prefetch_blocks` is 0, resolves the addresses they need
+/// ahead of the final processor, batching the lookups of up to `prefetch_blocks` queued blocks
+pub async fn evm_block_processor<R: AddressResolver>(
     mut block_rx: Receiver<ProcessingEVMBlock>,
     block_tx: Sender<ProcessingEVMBlock>,
+    native_to_evm_cache: Arc<NameToAddressCache<R>>,
+    prefetch_blocks: usize,
 ) -> Result<()> {
-    while let Some(mut block) = block_rx.recv().await {
-        debug!("Processing block {}", block.block_num);
-        block.deserialize();
-        if let Err(send_err) = block_tx.send(block).await {
-            error!(
-                "Failed to send block to final processor, error: {:?}",
-                send_err
-            );
-            break;
+    'blocks: while let Some(block) = block_rx.recv().await {
+        let mut blocks = vec![block];
+        while blocks.len() < prefetch_blocks {
+            match block_rx.try_recv() {
+                Ok(block) => blocks.push(block),
+                Err(_) => break,
+            }
+        }
+
+        let mut lookups = AddressLookups::default();
+        // Accounts linked by a block are known to the blocks after it in the batch
+        let mut linked = AddressLookups::default();
+        for block in blocks.iter_mut() {
+            debug!("Processing block {}", block.block_num);
+            block.deserialize();
+            if prefetch_blocks > 0 {
+                linked.extend(block.linked_accounts());
+                lookups.extend(block.address_lookups(&linked));
+            }
+        }
+
+        if !lookups.is_empty() {
+            let block_num = blocks[0].block_num;
+            // Lookups that fail here are retried when the block is finalized
+            match native_to_evm_cache.prefetch(block_num, &lookups).await {
+                Ok(fetched) => debug!(
+                    "Prefetched {fetched} addresses for {} blocks from #{block_num}",
+                    blocks.len()
+                ),
+                Err(error) => {
+                    warn!("Failed to prefetch addresses from block #{block_num}: {error}")
+                }
+            }
+        }
+
+        for block in blocks {
+            if let Err(send_err) = block_tx.send(block).await {
+                error!(
+                    "Failed to send block to final processor, error: {:?}",
+                    send_err
+                );
+                break 'blocks;
+            }
         }
     }
     info!("Exiting EVM block processor...");
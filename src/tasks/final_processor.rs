@@ -1,23 +1,27 @@
-use crate::block::{DecodedRow, TelosEVMBlock, WalletEvents};
+use crate::block::{AccountDiff, TelosEVMBlock, TranslatorWarning, WalletEvents};
 use crate::{
-    block::ProcessingEVMBlock, translator::TranslatorConfig,
+    block::ProcessingEVMBlock,
+    duplicate_hashes::DuplicateHashDetector,
+    resolver::AddressResolver,
+    snapshot::EvmSnapshot,
+    state::{EvmState, StateRootMode},
+    translator::{ChainIdPolicy, TranslatorConfig},
     types::translator_types::NameToAddressCache,
 };
-use alloy::primitives::{Address, Bytes, FixedBytes, U256};
+use alloy::primitives::{keccak256, FixedBytes, U256};
 use alloy_rlp::Encodable;
-use antelope::api::client::{APIClient, DefaultProvider};
 use eyre::{eyre, Context, Result};
 use hex::encode;
-use reth_telos_rpc_engine_api::structs::{
-    TelosAccountStateTableRow, TelosAccountTableRow, TelosEngineAPIExtraFields,
-};
+use reth_telos_rpc_engine_api::structs::TelosEngineAPIExtraFields;
 use std::str::FromStr;
+use std::sync::Arc;
 use tokio::{sync::mpsc, time::Instant};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-pub async fn final_processor(
+/// `native_to_evm_cache` is shared with the EVM block processor, which prefetches into it
+pub async fn final_processor<R: AddressResolver>(
     config: TranslatorConfig,
-    api_client: APIClient<DefaultProvider>,
+    native_to_evm_cache: Arc<NameToAddressCache<R>>,
     mut rx: mpsc::Receiver<ProcessingEVMBlock>,
     tx: Option<mpsc::Sender<TelosEVMBlock>>,
     stop_tx: mpsc::Sender<()>,
@@ -37,9 +41,44 @@ pub async fn final_processor(
         None => None,
     };
 
+    let known_contract_hashes = config
+        .known_contract_hashes
+        .iter()
+        .map(|hash| {
+            FixedBytes::from_str(hash)
+                .wrap_err("Known contract hash config is not a valid 32 byte hex string")
+        })
+        .collect::<Result<Vec<FixedBytes<32>>>>()?;
+    let known_abi_hashes = config
+        .known_abi_hashes
+        .iter()
+        .map(|hash| {
+            FixedBytes::from_str(hash)
+                .wrap_err("Known ABI hash config is not a valid 32 byte hex string")
+        })
+        .collect::<Result<Vec<FixedBytes<32>>>>()?;
+
     let mut validated = validate_hash.is_none();
+    let mut revision = config.start_revision;
+    let mut evm_state = match &config.state_snapshot {
+        Some(path) => {
+            let snapshot = EvmSnapshot::read(path).wrap_err("Failed to read state snapshot")?;
+            if snapshot.block_num + 1 != config.start_block {
+                return Err(eyre!(
+                    "State snapshot is at block {} but translation starts at block {}",
+                    snapshot.block_num,
+                    config.start_block
+                ));
+            }
+            snapshot.to_state()
+        }
+        None => EvmState::default(),
+    };
 
-    let native_to_evm_cache = NameToAddressCache::new(api_client);
+    let mut duplicate_detector = config
+        .duplicate_tx_hashes
+        .as_ref()
+        .map(DuplicateHashDetector::new);
     let stop_block = config
         .stop_block
         .map(|n| n + config.block_delta)
@@ -51,11 +90,112 @@ pub async fn final_processor(
         }
         debug!("Finalizing block #{}", block.block_num);
 
-        let (header, exec_payload) = block
-            .generate_evm_data(parent_hash, config.block_delta, &native_to_evm_cache)
-            .await;
+        // Checked before translating, the block may already use layouts of the new contract
+        if let Some(update) = &block.contract_update {
+            let abi_hash = update.abi.as_ref().map(keccak256);
+            info!(
+                "eosio.evm updated in block #{}, code hash: {:?}, abi hash: {:?}",
+                block.block_num, update.code_hash, abi_hash
+            );
+            let mut unknown = vec![];
+            if let Some(code_hash) = update.code_hash {
+                if !known_contract_hashes.is_empty() && !known_contract_hashes.contains(&code_hash)
+                {
+                    unknown.push(TranslatorWarning::UnknownContractCode {
+                        block_num: block.block_num,
+                        code_hash,
+                    });
+                }
+            }
+            if let Some(abi_hash) = abi_hash {
+                if !known_abi_hashes.is_empty() && !known_abi_hashes.contains(&abi_hash) {
+                    unknown.push(TranslatorWarning::UnknownContractAbi {
+                        block_num: block.block_num,
+                        abi_hash,
+                    });
+                }
+            }
+            for warning in unknown {
+                if config.halt_on_unknown_contract {
+                    error!("{warning}");
+                    return Err(eyre!("Unknown eosio.evm contract version!"));
+                }
+                warn!("{warning}");
+                block.warnings.push(warning);
+            }
+        }
+
+        let (mut header, mut exec_payload) = block
+            .generate_evm_data(
+                parent_hash,
+                config.block_delta,
+                revision,
+                &config.chain_spec,
+                native_to_evm_cache.as_ref(),
+            )
+            .await
+            .wrap_err_with(|| format!("Failed to translate block #{}", block.block_num))?;
+        native_to_evm_cache
+            .check_writes()
+            .wrap_err("Failed to persist resolved addresses")?;
+        if let Some((_, new_revision)) = block.new_revision {
+            revision = new_revision;
+        }
+
+        let mut computed_state_root = None;
+        if config.state_root != StateRootMode::Disabled {
+            evm_state.apply(&block.decoded_rows);
+            let state_root = evm_state.state_root();
+            debug!(
+                "State root of block #{}: {} ({} accounts)",
+                block.block_num,
+                state_root,
+                evm_state.account_count()
+            );
+            if config.state_root == StateRootMode::Enabled {
+                header.state_root = state_root;
+                exec_payload.state_root = state_root;
+                exec_payload.block_hash = header.hash_slow();
+            }
+            computed_state_root = Some(state_root);
+        }
 
         let block_hash = exec_payload.block_hash;
+        let native_block_id = block.block_id();
+
+        for violation in block.chain_id_violations(&config.chain_id_exceptions) {
+            match config.chain_id_policy {
+                ChainIdPolicy::Warn => warn!("{violation}"),
+                ChainIdPolicy::Record => {
+                    warn!("{violation}");
+                    block.warnings.push(violation);
+                }
+                ChainIdPolicy::Halt => {
+                    error!("{violation}");
+                    return Err(eyre!("Chain id validation failed!"));
+                }
+            }
+        }
+
+        for mismatch in block.create_address_mismatches() {
+            warn!("{mismatch}");
+            block.warnings.push(mismatch);
+        }
+
+        if let Some(detector) = &mut duplicate_detector {
+            let hashes = block.transactions.iter().map(|(trx, _receipt)| *trx.hash());
+            for duplicate in detector.check_block(block.block_num, hashes) {
+                warn!("{duplicate}");
+                block.warnings.push(duplicate);
+            }
+        }
+
+        if config.audit_config_row {
+            if let Some(mismatch) = block.config_row_mismatch() {
+                warn!("{mismatch}");
+                block.warnings.push(mismatch);
+            }
+        }
 
         debug!("Translator header: {:#?}", header);
 
@@ -90,6 +230,11 @@ pub async fn final_processor(
                 blocks_sec,
                 trx_sec
             );
+            let lookups = native_to_evm_cache.stats();
+            debug!(
+                "Address lookups served from deltas: {}, cache: {}, http: {}",
+                lookups.deltas, lookups.cache, lookups.http
+            );
             //info!("Block map is {} long", block_map.len());
             unlogged_blocks = 0;
             unlogged_transactions = 0;
@@ -99,51 +244,30 @@ pub async fn final_processor(
 
         let evm_block_num = header.number as u32;
 
-        let mut statediffs_account = vec![];
-        let mut statediffs_accountstate = vec![];
-
-        for row in block.decoded_rows {
-            match row {
-                DecodedRow::Account(acc_diff) => statediffs_account.push(TelosAccountTableRow {
-                    address: Address::from_slice(&acc_diff.address.data),
-                    account: acc_diff.account.to_string(),
-                    nonce: acc_diff.nonce,
-                    code: Bytes::from(acc_diff.code.clone()),
-                    balance: U256::from_be_slice(&acc_diff.balance.data),
-                }),
-                DecodedRow::AccountState(acc_state_diff) => {
-                    statediffs_accountstate.push(TelosAccountStateTableRow {
-                        address: native_to_evm_cache
-                            .get_index(acc_state_diff.index)
-                            .await
-                            .unwrap(),
-                        key: U256::from_be_slice(&acc_state_diff.key.data),
-                        value: U256::from_be_slice(&acc_state_diff.value.data),
-                    });
-                }
-                _ => (),
-            }
-        }
+        let state_diffs = block
+            .state_diffs(native_to_evm_cache.as_ref())
+            .await
+            .wrap_err("Failed to resolve account state address")?;
+        let removed_accounts = state_diffs.removed_accounts();
+        let statediffs_account = state_diffs
+            .accounts
+            .iter()
+            .map(AccountDiff::to_table_row)
+            .collect();
 
         let mut new_addresses_using_create = vec![];
         let mut new_addresses_using_openwallet = vec![];
 
-        for new_wallet in block.new_wallets {
+        for new_wallet in &block.new_wallets {
             match new_wallet {
                 WalletEvents::CreateWallet(trx_index, create_action) => new_addresses_using_create
                     .push((
-                        trx_index as u64,
-                        U256::from_be_slice(
-                            native_to_evm_cache
-                                .get(create_action.account.value())
-                                .await
-                                .unwrap()
-                                .as_slice(),
-                        ),
+                        *trx_index as u64,
+                        U256::from_be_slice(block.created_address(create_action).as_slice()),
                     )),
                 WalletEvents::OpenWallet(trx_index, openwallet_action) => {
                     new_addresses_using_openwallet.push((
-                        trx_index as u64,
+                        *trx_index as u64,
                         U256::from_be_slice(&openwallet_action.address.data),
                     ))
                 }
@@ -161,6 +285,8 @@ pub async fn final_processor(
         let completed_block = TelosEVMBlock {
             block_num: evm_block_num,
             block_hash,
+            native_block_num: block.block_num,
+            native_block_id,
             lib_num: block.lib_num,
             lib_hash: FixedBytes::from_slice(&block.lib_hash.data),
             transactions: block.transactions,
@@ -168,13 +294,17 @@ pub async fn final_processor(
             execution_payload: exec_payload,
             extra_fields: TelosEngineAPIExtraFields {
                 statediffs_account: Some(statediffs_account),
-                statediffs_accountstate: Some(statediffs_accountstate),
+                statediffs_accountstate: Some(state_diffs.account_states),
                 revision_changes: block.new_revision,
                 gasprice_changes: block.new_gas_price,
                 new_addresses_using_create: Some(new_addresses_using_create),
                 new_addresses_using_openwallet: Some(new_addresses_using_openwallet),
                 receipts,
             },
+            removed_accounts,
+            warnings: block.warnings,
+            computed_state_root,
+            contract_update: block.contract_update,
         };
 
         let block_num = block.block_num;
@@ -1,39 +1,102 @@
+use crate::chain_spec::TelosRules;
+use crate::resolver::{AddressResolver, ResolveError};
 use crate::rlp::telos_rlp_decode::TelosTxDecodable;
-use crate::types::evm_types::{PrintedReceipt, RawAction, TransferAction, WithdrawAction};
-use crate::types::translator_types::NameToAddressCache;
+use crate::types::evm_types::{
+    PrintedReceipt, RawAction, RevertReason, TransferAction, WithdrawAction,
+};
+use crate::types::ship_types::PermissionLevel;
+use crate::types::trace_types::{to_call_frames, CallFrame, InternalTransaction};
 use alloy::primitives::private::alloy_rlp::Error;
 use alloy::primitives::TxKind::Call;
-use alloy::primitives::{Address, Bloom, Log, Signature, B256, U256};
+use alloy::primitives::{Address, Bloom, Bytes, Log, Signature, B256, U256};
 use alloy_consensus::{SignableTransaction, TxEnvelope, TxLegacy};
 use alloy_rlp::Decodable;
 use antelope::chain::checksum::Checksum256;
+use antelope::chain::name::Name;
+use k256::ecdsa::{RecoveryId, Signature as K256Signature, VerifyingKey};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
 use num_bigint::{BigUint, ToBigUint};
 use reth_primitives::{Receipt, ReceiptWithBloom};
 
+/// Signature of an unsigned transaction: r is the native block id plus the transaction index,
+/// s the sender followed by padding. Unique within a block but not across blocks, `(id, i + 1)`
+/// and `(id + 1, i)` give the same r.
 pub fn make_unique_vrs(
     block_hash_native: Checksum256,
     sender_address: Address,
     trx_index: usize,
+    bad_sig_padding: bool,
 ) -> Signature {
     let v = 42u64;
     let hash_biguint = BigUint::from_bytes_be(&block_hash_native.data);
     let trx_index_biguint: BigUint = trx_index.to_biguint().unwrap();
     let r_biguint = hash_biguint + trx_index_biguint;
 
-    #[cfg(feature = "bad_sig_padding")]
-    let mut s_bytes = [0x00u8; 32];
-    #[cfg(not(feature = "bad_sig_padding"))]
-    let mut s_bytes = [0xffu8; 32];
+    let mut s_bytes = if bad_sig_padding {
+        [0x00u8; 32]
+    } else {
+        [0xffu8; 32]
+    };
     s_bytes[..20].copy_from_slice(sender_address.as_slice());
     let r = U256::from_be_slice(r_biguint.to_bytes_be().as_slice());
     let s = U256::from_be_slice(&s_bytes);
     Signature::from_rs_and_parity(r, s, v).expect("Failed to create signature")
 }
 
+/// Destination address of a deposit memo. The memo has to be exactly `0x` followed by 40 hex
+/// digits, in any case: EIP-55 checksums are optional and not verified, and nothing is trimmed.
+/// Any other memo deposits to the address linked to the sender and returns `None`.
+pub fn parse_deposit_memo(memo: &str) -> Option<Address> {
+    let hex = memo.strip_prefix("0x")?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    hex.parse().ok()
+}
+
+/// Recovers the signer of a user signed transaction from its signature and signing hash
+fn recover_signer(signature: &Signature, prehash: B256) -> Option<Address> {
+    let mut recovery_id = RecoveryId::new(signature.v().y_parity(), false);
+    let mut k256_signature = K256Signature::from_scalars(
+        signature.r().to_be_bytes::<32>(),
+        signature.s().to_be_bytes::<32>(),
+    )
+    .ok()?;
+    // k256 only accepts low s, the high s twin recovers the same key with the other parity
+    if let Some(normalized) = k256_signature.normalize_s() {
+        k256_signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), false);
+    }
+    let key = VerifyingKey::recover_from_prehash(prehash.as_slice(), &k256_signature, recovery_id)
+        .ok()?;
+    Some(Address::from_raw_public_key(
+        &key.to_encoded_point(false).as_bytes()[1..],
+    ))
+}
+
 #[derive(Clone)]
 pub struct TelosEVMTransaction {
     pub envelope: TxEnvelope,
     pub receipt: PrintedReceipt,
+    pub created_address: Option<Address>,
+    pub output: Bytes,
+    pub revert_reason: Option<RevertReason>,
+    pub errors: Vec<String>,
+    /// Signature was generated by the translator, unsigned raw transactions, deposits and withdraws
+    pub unsigned: bool,
+    /// Native action the transaction was translated from
+    pub native_origin: NativeOrigin,
+}
+
+/// Native transaction and action an EVM transaction comes from, not part of any hash
+#[derive(Debug, Clone, Default)]
+pub struct NativeOrigin {
+    pub trx_id: B256,
+    pub action_ordinal: u32,
+    pub global_sequence: u64,
+    pub authorization: Vec<PermissionLevel>,
+    /// Only raw actions name a RAM payer
+    pub ram_payer: Option<Name>,
 }
 
 impl TelosEVMTransaction {
@@ -43,69 +106,99 @@ impl TelosEVMTransaction {
         block_hash: Checksum256,
         raw: RawAction,
         receipt: PrintedReceipt,
+        native_origin: NativeOrigin,
+        rules: &TelosRules,
     ) -> Result<Self, Error> {
         // TODO: Check for unsigned transactions and handle correctly
         // TODO: Set trx_index properly for signed and unsigned transactions
         let tx_raw = &mut raw.tx.as_slice();
+        let sender = raw.sender.map(|sender| Address::from(sender.data));
+        // Only unsigned transactions carry a trusted sender in the action
+        let mut unsigned_sender = None;
 
-        if tx_raw[0] >= 0xc0 && tx_raw[0] <= 0xfe {
+        let envelope = if tx_raw[0] >= 0xc0 && tx_raw[0] <= 0xfe {
             let signed_legacy_result = TxLegacy::decode_signed_fields(tx_raw);
             if signed_legacy_result.is_err() {
-                let address = Address::from(
-                    raw.sender
-                        .expect("Failed to get address from sender in unsigned transaction")
-                        .data,
-                );
-                let sig = make_unique_vrs(block_hash, address, trx_index);
+                let address =
+                    sender.expect("Failed to get address from sender in unsigned transaction");
+                unsigned_sender = Some(address);
+                let sig = make_unique_vrs(block_hash, address, trx_index, rules.bad_sig_padding);
                 let unsigned_legacy =
                     TxLegacy::decode_telos_signed_fields(&mut raw.tx.clone().as_slice(), sig)?;
-                let envelope = TxEnvelope::Legacy(unsigned_legacy);
-                return Ok(TelosEVMTransaction { envelope, receipt });
-            }
-
-            let signed_legacy = signed_legacy_result.unwrap();
-            // Align with contract, if BOTH are zero it's zero and raw.sender is used
-            // https://github.com/telosnetwork/telos.evm/blob/9f2024a2a65e7c6b9bb98b36b368c359e24e6885/eosio.evm/include/eosio.evm/transaction.hpp#L205
-            if signed_legacy.signature().r().is_zero() && signed_legacy.signature().s().is_zero() {
-                let address = Address::from(
-                    raw.sender
-                        .expect("Failed to get address from sender in unsigned transaction")
-                        .data,
-                );
-                let sig = make_unique_vrs(block_hash, address, trx_index);
-                let unsigned_legacy = signed_legacy.strip_signature().into_signed(sig);
-                let envelope = TxEnvelope::Legacy(unsigned_legacy);
-                return Ok(TelosEVMTransaction { envelope, receipt });
+                TxEnvelope::Legacy(unsigned_legacy)
+            } else {
+                let signed_legacy = signed_legacy_result.unwrap();
+                // Align with contract, if BOTH are zero it's zero and raw.sender is used
+                // https://github.com/telosnetwork/telos.evm/blob/9f2024a2a65e7c6b9bb98b36b368c359e24e6885/eosio.evm/include/eosio.evm/transaction.hpp#L205
+                if signed_legacy.signature().r().is_zero()
+                    && signed_legacy.signature().s().is_zero()
+                {
+                    let address =
+                        sender.expect("Failed to get address from sender in unsigned transaction");
+                    unsigned_sender = Some(address);
+                    let sig =
+                        make_unique_vrs(block_hash, address, trx_index, rules.bad_sig_padding);
+                    TxEnvelope::Legacy(signed_legacy.strip_signature().into_signed(sig))
+                } else {
+                    TxEnvelope::Legacy(signed_legacy)
+                }
             }
-
-            let envelope = TxEnvelope::Legacy(signed_legacy);
-            Ok(TelosEVMTransaction { envelope, receipt })
         } else {
             let type_bit = tx_raw[0];
             match type_bit {
-                2 => {
-                    let envelope = TxEnvelope::decode(tx_raw).unwrap();
-                    Ok(TelosEVMTransaction { envelope, receipt })
-                }
+                2 => TxEnvelope::decode(tx_raw).unwrap(),
                 _ => panic!("tx type {} not implemented!", type_bit),
             }
+        };
+
+        if !rules.allows_tx_type(u8::from(envelope.tx_type())) {
+            return Err(Error::Custom(
+                "Transaction type not allowed by the chain spec",
+            ));
         }
+
+        let created_address = receipt.created_address;
+        let output = receipt.output.clone();
+        let revert_reason = if receipt.status != 1 {
+            RevertReason::decode(&output)
+        } else {
+            None
+        };
+        let errors = receipt.errors.clone();
+        Ok(TelosEVMTransaction {
+            envelope,
+            receipt,
+            created_address,
+            output,
+            revert_reason,
+            errors,
+            unsigned: unsigned_sender.is_some(),
+            native_origin,
+        })
     }
 
+    /// Deposit of a TLOS transfer to eosio.evm, transfers of other tokens are not deposits and
+    /// are skipped by the caller
+    #[allow(clippy::too_many_arguments)]
     pub async fn from_transfer(
         chain_id: u64,
         trx_index: usize,
         block_hash: Checksum256,
+        block_num: u32,
         action: TransferAction,
-        native_to_evm_cache: &NameToAddressCache,
-    ) -> Self {
-        let address: Address = if action.memo.starts_with("0x") {
-            action.memo.parse().unwrap()
-        } else {
-            native_to_evm_cache
-                .get(action.from.n)
-                .await
-                .expect("Failed to get address")
+        native_origin: NativeOrigin,
+        rules: &TelosRules,
+        resolver: &impl AddressResolver,
+    ) -> Result<Self, ResolveError> {
+        let address = match parse_deposit_memo(&action.memo) {
+            Some(address) => address,
+            None => resolver
+                .address_by_name(action.from.n, block_num)
+                .await?
+                .ok_or(ResolveError::UnknownName {
+                    name: action.from.n,
+                    block_num,
+                })?,
         };
 
         let value = U256::from(action.quantity.amount()) * U256::from(100_000_000_000_000i64);
@@ -120,26 +213,33 @@ impl TelosEVMTransaction {
             input: Default::default(),
         };
 
-        let sig = make_unique_vrs(block_hash, Address::ZERO, trx_index);
+        let sig = make_unique_vrs(block_hash, Address::ZERO, trx_index, rules.bad_sig_padding);
         let signed_legacy = tx_legacy.clone().into_signed(sig);
         let mut raw: Vec<u8> = vec![];
         tx_legacy.encode_with_signature_fields(&sig, &mut raw);
         let envelope = TxEnvelope::Legacy(signed_legacy);
-        TelosEVMTransaction {
+        Ok(TelosEVMTransaction {
             envelope,
             receipt: PrintedReceipt {
-                charged_gas: "".to_string(),
+                charged_gas: U256::ZERO,
                 trx_index: trx_index as u16,
                 block: 0,
                 status: 1,
                 epoch: 0,
-                createdaddr: "".to_string(),
-                gasused: "5208".to_string(),
+                created_address: None,
+                gas_used: 21_000,
                 logs: vec![],
-                output: "".to_string(),
-                errors: None,
+                output: Bytes::new(),
+                errors: vec![],
+                itxs: vec![],
             },
-        }
+            created_address: None,
+            output: Bytes::new(),
+            revert_reason: None,
+            errors: vec![],
+            unsigned: true,
+            native_origin,
+        })
     }
 
     pub async fn from_withdraw_no_cache(
@@ -148,6 +248,8 @@ impl TelosEVMTransaction {
         block_hash: Checksum256,
         action: WithdrawAction,
         address: Address,
+        native_origin: NativeOrigin,
+        rules: &TelosRules,
     ) -> Self {
         let value = U256::from(action.quantity.amount()) * U256::from(100_000_000_000_000i64);
         let tx_legacy = TxLegacy {
@@ -160,41 +262,103 @@ impl TelosEVMTransaction {
             input: Default::default(),
         };
 
-        let sig = make_unique_vrs(block_hash, address, trx_index);
+        let sig = make_unique_vrs(block_hash, address, trx_index, rules.bad_sig_padding);
         let signed_legacy = tx_legacy.into_signed(sig);
         let envelope = TxEnvelope::Legacy(signed_legacy);
         TelosEVMTransaction {
             envelope,
             receipt: PrintedReceipt {
-                charged_gas: "".to_string(),
+                charged_gas: U256::ZERO,
                 trx_index: trx_index as u16,
                 block: 0,
                 status: 1,
                 epoch: 0,
-                createdaddr: "".to_string(),
-                gasused: "5208".to_string(),
+                created_address: None,
+                gas_used: 21_000,
                 logs: vec![],
-                output: "".to_string(),
-                errors: None,
+                output: Bytes::new(),
+                errors: vec![],
+                itxs: vec![],
             },
+            created_address: None,
+            output: Bytes::new(),
+            revert_reason: None,
+            errors: vec![],
+            unsigned: true,
+            native_origin,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn from_withdraw(
         chain_id: u64,
         trx_index: usize,
         block_hash: Checksum256,
+        block_num: u32,
         action: WithdrawAction,
-        native_to_evm_cache: &NameToAddressCache,
-    ) -> Self {
-        let address = native_to_evm_cache
-            .get(action.to.n)
-            .await
-            .expect("Failed to get address");
-        TelosEVMTransaction::from_withdraw_no_cache(
-            chain_id, trx_index, block_hash, action, address,
+        native_origin: NativeOrigin,
+        rules: &TelosRules,
+        resolver: &impl AddressResolver,
+    ) -> Result<Self, ResolveError> {
+        let address = resolver
+            .address_by_name(action.to.n, block_num)
+            .await?
+            .ok_or(ResolveError::UnknownName {
+                name: action.to.n,
+                block_num,
+            })?;
+        Ok(TelosEVMTransaction::from_withdraw_no_cache(
+            chain_id,
+            trx_index,
+            block_hash,
+            action,
+            address,
+            native_origin,
+            rules,
         )
-        .await
+        .await)
+    }
+
+    /// Chain id of a user signed transaction, `None` inside if it was signed before EIP-155
+    pub fn signed_chain_id(&self) -> Option<Option<u64>> {
+        if self.unsigned {
+            return None;
+        }
+        match &self.envelope {
+            TxEnvelope::Legacy(stx) => Some(stx.tx().chain_id),
+            TxEnvelope::Eip1559(stx) => Some(Some(stx.tx().chain_id)),
+            _ => None,
+        }
+    }
+
+    /// Sender of the transaction, recovered from the signature of user signed transactions.
+    /// Signatures generated by the translator carry the sender in the first 20 bytes of s.
+    pub fn sender(&self) -> Option<Address> {
+        let (signature, prehash) = match &self.envelope {
+            TxEnvelope::Legacy(stx) => (stx.signature(), stx.signature_hash()),
+            TxEnvelope::Eip1559(stx) => (stx.signature(), stx.signature_hash()),
+            _ => return None,
+        };
+        if self.unsigned {
+            return Some(Address::from_slice(
+                &signature.s().to_be_bytes::<32>()[..20],
+            ));
+        }
+        recover_signer(signature, prehash)
+    }
+
+    /// Address a create transaction deploys its contract to, derived from the sender and
+    /// nonce. `None` for calls and when the sender can't be recovered.
+    pub fn expected_created_address(&self) -> Option<Address> {
+        let (to, nonce) = match &self.envelope {
+            TxEnvelope::Legacy(stx) => (stx.tx().to, stx.tx().nonce),
+            TxEnvelope::Eip1559(stx) => (stx.tx().to, stx.tx().nonce),
+            _ => return None,
+        };
+        if to.is_call() {
+            return None;
+        }
+        Some(self.sender()?.create(nonce))
     }
 
     pub fn hash(&self) -> &B256 {
@@ -205,12 +369,21 @@ impl TelosEVMTransaction {
         self.receipt.logs.clone()
     }
 
+    pub fn internal_transactions(&self) -> &[InternalTransaction] {
+        &self.receipt.itxs
+    }
+
+    /// Internal call tree as geth `callTracer` style frames
+    pub fn call_frames(&self) -> Vec<CallFrame> {
+        to_call_frames(&self.receipt.itxs)
+    }
+
     pub fn gas_used(&self) -> U256 {
-        self.receipt.gasused.parse().unwrap()
+        U256::from(self.receipt.gas_used)
     }
 
     pub fn receipt(&self, cumulative_gas_used: u64) -> ReceiptWithBloom {
-        let tx_gas_used = u64::from_str_radix(&self.receipt.gasused, 16).unwrap();
+        let tx_gas_used = self.receipt.gas_used;
         let logs = self.receipt.logs.clone();
         let mut bloom = Bloom::default();
         for log in &logs {
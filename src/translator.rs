@@ -1,11 +1,17 @@
+use crate::account_history::AccountHistory;
+use crate::address_store::AddressStore;
 use crate::block::{ProcessingEVMBlock, TelosEVMBlock};
+use crate::chain_spec::TelosChainSpec;
+use crate::duplicate_hashes::DuplicateHashConfig;
+use crate::resolver::{AddressResolver, HttpResolver, HttpRetryConfig};
+use crate::state::StateRootMode;
 use crate::tasks::{evm_block_processor, final_processor, raw_deserializer, ship_reader};
-use antelope::api::client::APIClient;
-use antelope::api::default_provider::DefaultProvider;
+use crate::types::translator_types::{AddressCacheConfig, NameToAddressCache};
 use eyre::{eyre, Context, Result};
 use futures_util::future::join_all;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_tungstenite::connect_async;
 use tracing::info;
@@ -25,6 +31,11 @@ pub struct TranslatorConfig {
 
     pub http_endpoint: String,
     pub ship_endpoint: String,
+    /// Tried in order when http_endpoint fails address lookups
+    #[serde(default)]
+    pub http_fallback_endpoints: Vec<String>,
+    #[serde(default)]
+    pub http_retry: HttpRetryConfig,
 
     #[serde(default = "default_channel_size")]
     pub raw_message_channel_size: usize,
@@ -32,6 +43,115 @@ pub struct TranslatorConfig {
     pub block_message_channel_size: usize,
     #[serde(default = "default_channel_size")]
     pub final_message_channel_size: usize,
+
+    #[serde(default)]
+    pub chain_id_policy: ChainIdPolicy,
+    #[serde(default)]
+    pub chain_id_exceptions: Vec<ChainIdException>,
+
+    /// Compare gas used and transaction count of each block against the eosio.evm config row
+    #[serde(default)]
+    pub audit_config_row: bool,
+
+    /// Code hashes of the eosio.evm versions the translator decodes correctly, code updates to
+    /// any other hash are reported. Empty accepts any code.
+    #[serde(default)]
+    pub known_contract_hashes: Vec<String>,
+    /// keccak256 hashes of the packed eosio.evm ABIs the translator decodes correctly, ABI
+    /// updates to any other hash are reported. Empty accepts any ABI.
+    #[serde(default)]
+    pub known_abi_hashes: Vec<String>,
+    /// Stop the translator on code or ABI updates to an unknown hash instead of only reporting
+    /// them
+    #[serde(default)]
+    pub halt_on_unknown_contract: bool,
+
+    /// Report transaction hashes repeated within a window of blocks, disabled when unset
+    #[serde(default)]
+    pub duplicate_tx_hashes: Option<DuplicateHashConfig>,
+
+    #[serde(default)]
+    pub chain_spec: TelosChainSpec,
+    /// eosio.evm revision in effect at start_block
+    #[serde(default)]
+    pub start_revision: u64,
+
+    /// State is built from the table deltas starting at start_block, so anything other than
+    /// disabled needs to start at genesis
+    #[serde(default)]
+    pub state_root: StateRootMode,
+    /// Snapshot file of the eosio.evm tables at start_block - 1, seeds the tracked state
+    #[serde(default)]
+    pub state_snapshot: Option<String>,
+
+    /// File recording the eosio.evm account table over the translated blocks, used to resolve
+    /// native names and account indexes as of the block being translated
+    #[serde(default)]
+    pub account_history: Option<String>,
+
+    #[serde(default)]
+    pub address_cache: AddressCacheConfig,
+}
+
+/// What to do when a signed transaction doesn't carry the configured chain id
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainIdPolicy {
+    /// Log the violation and continue
+    #[default]
+    Warn,
+    /// Log the violation and add it to the block warnings
+    Record,
+    /// Stop the translator
+    Halt,
+}
+
+/// Signed transactions allowed to not match the configured chain id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ChainIdException {
+    /// Transactions signed without chain id (pre EIP-155)
+    PreEip155,
+    /// Any transaction in the native block range, both ends inclusive
+    BlockRange { start_block: u32, end_block: u32 },
+}
+
+impl ChainIdException {
+    pub fn allows(&self, block_num: u32, chain_id: Option<u64>) -> bool {
+        match self {
+            ChainIdException::PreEip155 => chain_id.is_none(),
+            ChainIdException::BlockRange {
+                start_block,
+                end_block,
+            } => (*start_block..=*end_block).contains(&block_num),
+        }
+    }
+}
+
+/// Layered address resolver of the translated blocks, `fallback` resolves the addresses of
+/// accounts not found in them
+fn address_cache<R: AddressResolver>(
+    config: &TranslatorConfig,
+    fallback: R,
+) -> Result<NameToAddressCache<R>> {
+    let mut native_to_evm_cache = NameToAddressCache::with_capacity(
+        fallback,
+        config.address_cache.capacity,
+        config.address_cache.eviction,
+    );
+    if let Some(path) = &config.address_cache.path {
+        let store = AddressStore::open(path).wrap_err("Failed to load address store")?;
+        native_to_evm_cache = native_to_evm_cache.with_store(store);
+    }
+    if let Some(path) = &config.account_history {
+        let history = AccountHistory::open(path).wrap_err("Failed to load account history")?;
+        info!(
+            "Loaded account history up to block {:?}",
+            history.last_block()
+        );
+        native_to_evm_cache = native_to_evm_cache.with_history(history);
+    }
+    Ok(native_to_evm_cache)
 }
 
 pub struct Translator {
@@ -49,11 +169,26 @@ impl Translator {
         stop_tx: mpsc::Sender<()>,
         stop_rx: mpsc::Receiver<()>,
     ) -> Result<()> {
-        let api_client =
-            APIClient::<DefaultProvider>::default_provider(self.config.http_endpoint.clone())
-                .map_err(|error| eyre!(error))
-                .wrap_err("Failed to create API client")?;
+        let endpoints = std::iter::once(&self.config.http_endpoint)
+            .chain(&self.config.http_fallback_endpoints)
+            .cloned()
+            .collect::<Vec<_>>();
+        let resolver = HttpResolver::with_endpoints(&endpoints, self.config.http_retry.clone())
+            .wrap_err("Failed to create API client")?;
+
+        self.launch_with_resolver(resolver, output_tx, stop_tx, stop_rx)
+            .await
+    }
 
+    /// Like `launch`, with `resolver` answering the address lookups not covered by the
+    /// translated blocks instead of the http_endpoint node
+    pub async fn launch_with_resolver<R: AddressResolver + 'static>(
+        &mut self,
+        resolver: R,
+        output_tx: Option<mpsc::Sender<TelosEVMBlock>>,
+        stop_tx: mpsc::Sender<()>,
+        stop_rx: mpsc::Receiver<()>,
+    ) -> Result<()> {
         let (ws_stream, _) = connect_async(&self.config.ship_endpoint)
             .await
             .map_err(|_| {
@@ -75,16 +210,23 @@ impl Translator {
         let (finalize_tx, finalize_rx) =
             mpsc::channel::<ProcessingEVMBlock>(self.config.final_message_channel_size);
 
+        let native_to_evm_cache = Arc::new(address_cache(&self.config, resolver)?);
+
         // Start the final processing task
         let final_processor_handle = tokio::spawn(final_processor(
             self.config.clone(),
-            api_client,
+            native_to_evm_cache.clone(),
             finalize_rx,
             output_tx,
             stop_tx,
         ));
 
-        let evm_block_processor_handle = tokio::spawn(evm_block_processor(process_rx, finalize_tx));
+        let evm_block_processor_handle = tokio::spawn(evm_block_processor(
+            process_rx,
+            finalize_tx,
+            native_to_evm_cache,
+            self.config.address_cache.prefetch_blocks,
+        ));
 
         let raw_deserializer_handle = tokio::spawn(raw_deserializer(
             self.config.clone(),
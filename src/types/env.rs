@@ -3,7 +3,11 @@ use std::str::FromStr;
 use alloy::primitives::FixedBytes;
 use lazy_static::lazy_static;
 
-use crate::translator::{default_channel_size, TranslatorConfig};
+use crate::chain_spec::TelosChainSpec;
+use crate::resolver::HttpRetryConfig;
+use crate::state::StateRootMode;
+use crate::translator::{default_channel_size, ChainIdPolicy, TranslatorConfig};
+use crate::types::translator_types::AddressCacheConfig;
 
 pub const ANTELOPE_EPOCH_MS: u64 = 946684800000;
 pub const ANTELOPE_INTERVAL_MS: u64 = 500;
@@ -26,10 +30,27 @@ lazy_static! {
 
         http_endpoint: String::from("http://127.0.0.1:8888"),
         ship_endpoint: String::from("ws://127.0.0.1:29999"),
+        http_fallback_endpoints: vec![],
+        http_retry: HttpRetryConfig::default(),
 
         raw_message_channel_size: default_channel_size(),
         block_message_channel_size: default_channel_size(),
-        final_message_channel_size: default_channel_size()
+        final_message_channel_size: default_channel_size(),
+
+        chain_id_policy: ChainIdPolicy::default(),
+        chain_id_exceptions: vec![],
+        audit_config_row: false,
+        known_contract_hashes: vec![],
+        known_abi_hashes: vec![],
+        halt_on_unknown_contract: false,
+        duplicate_tx_hashes: None,
+
+        chain_spec: TelosChainSpec::default(),
+        start_revision: 0,
+        state_root: StateRootMode::Disabled,
+        state_snapshot: None,
+        account_history: None,
+        address_cache: AddressCacheConfig::default()
     };
     pub static ref MAINNET_DEPLOY_CONFIG: TranslatorConfig = TranslatorConfig {
         chain_id: 40,
@@ -45,10 +66,27 @@ lazy_static! {
 
         http_endpoint: String::from("http://127.0.0.1:8888"),
         ship_endpoint: String::from("ws://127.0.0.1:29999"),
+        http_fallback_endpoints: vec![],
+        http_retry: HttpRetryConfig::default(),
 
         raw_message_channel_size: default_channel_size(),
         block_message_channel_size: default_channel_size(),
-        final_message_channel_size: default_channel_size()
+        final_message_channel_size: default_channel_size(),
+
+        chain_id_policy: ChainIdPolicy::default(),
+        chain_id_exceptions: vec![],
+        audit_config_row: false,
+        known_contract_hashes: vec![],
+        known_abi_hashes: vec![],
+        halt_on_unknown_contract: false,
+        duplicate_tx_hashes: None,
+
+        chain_spec: TelosChainSpec::default(),
+        start_revision: 0,
+        state_root: StateRootMode::Disabled,
+        state_snapshot: None,
+        account_history: None,
+        address_cache: AddressCacheConfig::default()
     };
     pub static ref TESTNET_GENESIS_CONFIG: TranslatorConfig = TranslatorConfig {
         chain_id: 41,
@@ -64,10 +102,27 @@ lazy_static! {
 
         http_endpoint: String::from("http://127.0.0.1:8888"),
         ship_endpoint: String::from("ws://127.0.0.1:29999"),
+        http_fallback_endpoints: vec![],
+        http_retry: HttpRetryConfig::default(),
 
         raw_message_channel_size: default_channel_size(),
         block_message_channel_size: default_channel_size(),
-        final_message_channel_size: default_channel_size()
+        final_message_channel_size: default_channel_size(),
+
+        chain_id_policy: ChainIdPolicy::default(),
+        chain_id_exceptions: vec![],
+        audit_config_row: false,
+        known_contract_hashes: vec![],
+        known_abi_hashes: vec![],
+        halt_on_unknown_contract: false,
+        duplicate_tx_hashes: None,
+
+        chain_spec: TelosChainSpec::default(),
+        start_revision: 0,
+        state_root: StateRootMode::Disabled,
+        state_snapshot: None,
+        account_history: None,
+        address_cache: AddressCacheConfig::default()
     };
     pub static ref TESTNET_DEPLOY_CONFIG: TranslatorConfig = TranslatorConfig {
         chain_id: 41,
@@ -81,9 +136,26 @@ lazy_static! {
 
         http_endpoint: String::from("http://127.0.0.1:8888"),
         ship_endpoint: String::from("ws://127.0.0.1:29999"),
+        http_fallback_endpoints: vec![],
+        http_retry: HttpRetryConfig::default(),
 
         raw_message_channel_size: default_channel_size(),
         block_message_channel_size: default_channel_size(),
-        final_message_channel_size: default_channel_size()
+        final_message_channel_size: default_channel_size(),
+
+        chain_id_policy: ChainIdPolicy::default(),
+        chain_id_exceptions: vec![],
+        audit_config_row: false,
+        known_contract_hashes: vec![],
+        known_abi_hashes: vec![],
+        halt_on_unknown_contract: false,
+        duplicate_tx_hashes: None,
+
+        chain_spec: TelosChainSpec::default(),
+        start_revision: 0,
+        state_root: StateRootMode::Disabled,
+        state_snapshot: None,
+        account_history: None,
+        address_cache: AddressCacheConfig::default()
     };
 }
@@ -1,5 +1,11 @@
+use crate::types::trace_types::{
+    deserialize_address, deserialize_hex_bytes, deserialize_hex_u256, deserialize_hex_u64,
+    deserialize_internal_transactions, deserialize_optional_address, strip_hex_prefix,
+    InternalTransaction,
+};
 use alloy::primitives::aliases::BlockTimestamp;
-use alloy::primitives::{Address, Log, B256};
+use alloy::primitives::{keccak256, Address, Bytes, Log, B256, U256};
+use alloy_rlp::Encodable;
 use antelope::chain::asset::Asset;
 use antelope::chain::binary_extension::BinaryExtension;
 use antelope::chain::checksum::{Checksum160, Checksum256};
@@ -8,9 +14,12 @@ use antelope::chain::time::TimePoint;
 use antelope::chain::Packer;
 use antelope::serializer::Decoder;
 use antelope::serializer::Encoder;
-use antelope::util::hex_to_bytes;
 use antelope::StructPacker;
 use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, StructPacker)]
 pub struct RawAction {
@@ -103,84 +112,182 @@ pub struct CreateAction {
     pub data: String,
 }
 
+impl CreateAction {
+    /// Address eosio.evm links to the created account, the last 20 bytes of the keccak256 hash
+    /// of the RLP list `[account.value, data]`
+    pub fn address(&self) -> Address {
+        let account = self.account.value();
+        let data = self.data.as_bytes();
+        let mut encoded = vec![];
+        alloy_rlp::Header {
+            list: true,
+            payload_length: account.length() + data.length(),
+        }
+        .encode(&mut encoded);
+        account.encode(&mut encoded);
+        data.encode(&mut encoded);
+        Address::from_slice(&keccak256(encoded)[12..])
+    }
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct PrintedReceipt {
-    pub charged_gas: String,
+    #[serde(deserialize_with = "deserialize_hex_u256")]
+    pub charged_gas: U256,
     pub trx_index: u16,
     pub block: u64,
     pub status: u8,
     pub epoch: u64,
-    pub createdaddr: String,
-    pub gasused: String,
+    /// Address of the contract deployed by this transaction, `None` if nothing was created
+    #[serde(
+        rename = "createdaddr",
+        deserialize_with = "deserialize_optional_address"
+    )]
+    pub created_address: Option<Address>,
+    #[serde(rename = "gasused", deserialize_with = "deserialize_hex_u64")]
+    pub gas_used: u64,
     #[serde(deserialize_with = "deserialize_logs")]
     pub logs: Vec<Log>,
-    pub output: String,
-    pub errors: Option<Vec<String>>,
-    // pub itxs: any[], // Define struct for this
+    #[serde(deserialize_with = "deserialize_hex_bytes")]
+    pub output: Bytes,
+    #[serde(default, deserialize_with = "deserialize_errors")]
+    pub errors: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_internal_transactions")]
+    pub itxs: Vec<InternalTransaction>,
 }
 
+#[derive(Debug)]
+pub enum PrintedReceiptError {
+    /// No `RCPT{{...}}RCPT` segment in the console
+    NotFound,
+    /// Segment found but its content is not a valid receipt
+    Invalid(serde_json::Error),
+}
+
+impl fmt::Display for PrintedReceiptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrintedReceiptError::NotFound => write!(f, "No printed receipt found in console"),
+            PrintedReceiptError::Invalid(error) => write!(f, "Invalid printed receipt: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PrintedReceiptError {}
+
 fn deserialize_logs<'de, D>(deserializer: D) -> Result<Vec<Log>, D::Error>
 where
     D: Deserializer<'de>,
 {
     #[derive(Deserialize)]
     struct LogHelper {
-        address: String,
-        data: String,
-        topics: Vec<String>,
-    }
-
-    impl LogHelper {
-        fn address(&self) -> Address {
-            let padded = format!("{:0>40}", self.address);
-            padded.parse().expect("Invalid address")
-        }
+        #[serde(deserialize_with = "deserialize_address")]
+        address: Address,
+        #[serde(deserialize_with = "deserialize_hex_bytes")]
+        data: Bytes,
+        #[serde(deserialize_with = "deserialize_topics")]
+        topics: Vec<B256>,
     }
 
     let log_helpers = Vec::<LogHelper>::deserialize(deserializer)?;
-    let mut logs = vec![];
-    for log in log_helpers {
-        let address = log.address();
-        let topics = log
-            .topics
-            .into_iter()
-            .map(|topic| to_b256(&topic))
-            .collect();
-        let data = log.data.parse().expect("Invalid data");
-        logs.push(Log::new(address, topics, data).unwrap());
-    }
-    Ok(logs)
+    log_helpers
+        .into_iter()
+        .map(|log| {
+            Log::new(log.address, log.topics, log.data)
+                .ok_or_else(|| serde::de::Error::custom("Too many log topics"))
+        })
+        .collect()
 }
 
-fn to_b256(s: &str) -> B256 {
-    let binding = hex_to_bytes(s);
-    let b256_slice = binding.as_slice();
-    if b256_slice.len() <= 32 {
-        B256::left_padding_from(b256_slice)
-    } else {
-        panic!("Invalid B256 length");
-    }
+/// Topics are printed without leading zeros, empty for a zero topic
+fn deserialize_topics<'de, D>(deserializer: D) -> Result<Vec<B256>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|topic| {
+            let topic = strip_hex_prefix(topic);
+            if topic.len() > 64 {
+                return Err(serde::de::Error::custom("Invalid topic length"));
+            }
+            format!("{:0>64}", topic)
+                .parse()
+                .map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
+fn deserialize_errors<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<Vec<String>>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 impl PrintedReceipt {
-    pub fn from_console(console: String) -> Option<Self> {
+    /// Parses the receipt printed by eosio.evm, prints made by the contract before or after it
+    /// are ignored. If the console holds several `RCPT{{...}}RCPT` segments the last valid one
+    /// is used, as the contract prints the receipt at the end of the raw action.
+    pub fn from_console(console: String) -> Result<Self, PrintedReceiptError> {
         let start_pattern = "RCPT{{";
         let end_pattern = "}}RCPT";
 
-        if let Some(start) = console.find(start_pattern) {
+        let mut result = Err(PrintedReceiptError::NotFound);
+        let mut remaining = console.as_str();
+        while let Some(start) = remaining.find(start_pattern) {
             let start_index = start + start_pattern.len();
-            if let Some(end) = console[start_index..].find(end_pattern) {
-                let end_index = start_index + end;
-                let extracted = &console[start_index..end_index];
-                let printed_receipt = serde_json::from_str::<PrintedReceipt>(extracted).unwrap();
-                Some(printed_receipt)
-            } else {
-                println!("End pattern not found.");
-                None
+            let Some(end) = remaining[start_index..].find(end_pattern) else {
+                break;
+            };
+            let end_index = start_index + end;
+            match serde_json::from_str::<PrintedReceipt>(&remaining[start_index..end_index]) {
+                Ok(receipt) => result = Ok(receipt),
+                Err(error) if result.is_err() => result = Err(PrintedReceiptError::Invalid(error)),
+                Err(_) => {}
             }
+            remaining = &remaining[end_index + end_pattern.len()..];
+        }
+        result
+    }
+}
+
+/// Solidity revert payload found in the output of a failed transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `Error(string)`, used by `revert("...")` and `require(cond, "...")`
+    Error(String),
+    /// `Panic(uint256)`, used by failed asserts, arithmetic overflows, etc
+    Panic(U256),
+}
+
+impl RevertReason {
+    pub fn decode(output: &[u8]) -> Option<Self> {
+        if output.len() < 4 {
+            return None;
+        }
+        let (selector, data) = output.split_at(4);
+        if selector == ERROR_SELECTOR {
+            let offset = usize::try_from(U256::from_be_slice(data.get(..32)?)).ok()?;
+            let start = offset.checked_add(32)?;
+            let len = usize::try_from(U256::from_be_slice(data.get(offset..start)?)).ok()?;
+            let message = data.get(start..start.checked_add(len)?)?;
+            Some(RevertReason::Error(
+                String::from_utf8_lossy(message).into_owned(),
+            ))
+        } else if selector == PANIC_SELECTOR {
+            Some(RevertReason::Panic(U256::from_be_slice(data.get(..32)?)))
         } else {
-            println!("Start pattern not found.");
             None
         }
     }
 }
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Error(message) => write!(f, "execution reverted: {message}"),
+            RevertReason::Panic(code) => write!(f, "panic: {code:#x}"),
+        }
+    }
+}
@@ -2,4 +2,5 @@ pub mod env;
 pub mod evm_types;
 pub mod names;
 pub mod ship_types;
+pub mod trace_types;
 pub mod translator_types;
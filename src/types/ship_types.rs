@@ -11,6 +11,15 @@ use serde::{Deserialize, Serialize};
 use std::option::Option;
 use tokio_tungstenite::tungstenite::Message;
 
+/// Values of `transaction_receipt_header::status_enum`
+pub mod transaction_status {
+    pub const EXECUTED: u8 = 0;
+    pub const SOFT_FAIL: u8 = 1;
+    pub const HARD_FAIL: u8 = 2;
+    pub const DELAYED: u8 = 3;
+    pub const EXPIRED: u8 = 4;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, EnumPacker)]
 pub enum ShipRequest {
     GetStatus(GetStatusRequestV0),
@@ -56,6 +65,21 @@ pub enum ContractRow {
     V0(ContractRowV0),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, EnumPacker)]
+pub enum Account {
+    V0(AccountV0),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, EnumPacker)]
+pub enum AccountMetadata {
+    V0(AccountMetadataV0),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, EnumPacker)]
+pub enum Code {
+    V0(CodeV0),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, EnumPacker)]
 pub enum ActionReceipt {
     V0(ActionReceiptV0),
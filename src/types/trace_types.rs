@@ -0,0 +1,254 @@
+use alloy::primitives::{Address, Bytes, U256};
+use antelope::util::hex_to_bytes;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallType {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+    SelfDestruct,
+    #[serde(other)]
+    Unknown,
+}
+
+impl CallType {
+    /// Opcode style name used by geth tracers
+    pub fn geth_name(&self) -> &'static str {
+        match self {
+            CallType::Call => "CALL",
+            CallType::CallCode => "CALLCODE",
+            CallType::DelegateCall => "DELEGATECALL",
+            CallType::StaticCall => "STATICCALL",
+            CallType::Create => "CREATE",
+            CallType::Create2 => "CREATE2",
+            CallType::SelfDestruct => "SELFDESTRUCT",
+            CallType::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// Internal transaction as printed by eosio.evm inside the `RCPT{{...}}RCPT` console
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InternalTransaction {
+    pub call_type: CallType,
+    #[serde(deserialize_with = "deserialize_address")]
+    pub from: Address,
+    #[serde(default, deserialize_with = "deserialize_optional_address")]
+    pub to: Option<Address>,
+    #[serde(default, deserialize_with = "deserialize_hex_u256")]
+    pub value: U256,
+    #[serde(default, deserialize_with = "deserialize_hex_u64")]
+    pub gas: u64,
+    #[serde(default, deserialize_with = "deserialize_hex_u64")]
+    pub gas_used: u64,
+    #[serde(default, deserialize_with = "deserialize_hex_bytes")]
+    pub input: Bytes,
+    #[serde(default, deserialize_with = "deserialize_hex_bytes")]
+    pub output: Bytes,
+    #[serde(default)]
+    pub trace_address: Vec<usize>,
+    #[serde(default, deserialize_with = "deserialize_number")]
+    pub depth: usize,
+}
+
+/// Nested call frame in the format produced by geth's `callTracer`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub from: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas: U256,
+    pub gas_used: U256,
+    pub input: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Bytes>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+}
+
+impl From<&InternalTransaction> for CallFrame {
+    fn from(itx: &InternalTransaction) -> Self {
+        CallFrame {
+            typ: itx.call_type.geth_name().to_string(),
+            from: itx.from,
+            to: itx.to,
+            value: itx.value,
+            gas: U256::from(itx.gas),
+            gas_used: U256::from(itx.gas_used),
+            input: itx.input.clone(),
+            output: (!itx.output.is_empty()).then(|| itx.output.clone()),
+            calls: vec![],
+        }
+    }
+}
+
+/// Rebuilds the call tree out of the flat internal transaction list using the trace addresses,
+/// returns the top level frames, normally just one
+pub fn to_call_frames(itxs: &[InternalTransaction]) -> Vec<CallFrame> {
+    let positions: HashMap<&[usize], usize> = itxs
+        .iter()
+        .enumerate()
+        .map(|(i, itx)| (itx.trace_address.as_slice(), i))
+        .collect();
+
+    let mut roots = vec![];
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, itx) in itxs.iter().enumerate() {
+        let parent = itx
+            .trace_address
+            .split_last()
+            .and_then(|(_, parent_address)| positions.get(parent_address));
+        match parent {
+            Some(&parent) if parent != i => children.entry(parent).or_default().push(i),
+            _ => roots.push(i),
+        }
+    }
+
+    fn build(
+        itxs: &[InternalTransaction],
+        children: &HashMap<usize, Vec<usize>>,
+        index: usize,
+    ) -> CallFrame {
+        let mut frame = CallFrame::from(&itxs[index]);
+        if let Some(child_indexes) = children.get(&index) {
+            let mut child_indexes = child_indexes.clone();
+            child_indexes.sort_by(|a, b| itxs[*a].trace_address.cmp(&itxs[*b].trace_address));
+            frame.calls = child_indexes
+                .into_iter()
+                .map(|child| build(itxs, children, child))
+                .collect();
+        }
+        frame
+    }
+
+    roots.sort_by(|a, b| itxs[*a].trace_address.cmp(&itxs[*b].trace_address));
+    roots
+        .into_iter()
+        .map(|root| build(itxs, &children, root))
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(u64),
+}
+
+pub(crate) fn strip_hex_prefix(s: &str) -> &str {
+    s.strip_prefix("0x").unwrap_or(s)
+}
+
+pub(crate) fn deserialize_address<'de, D>(deserializer: D) -> Result<Address, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_optional_address(deserializer)?
+        .ok_or_else(|| serde::de::Error::custom("Empty address"))
+}
+
+pub(crate) fn deserialize_optional_address<'de, D>(
+    deserializer: D,
+) -> Result<Option<Address>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let address = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    let address = strip_hex_prefix(&address);
+    if address.is_empty() {
+        return Ok(None);
+    }
+    format!("{:0>40}", address)
+        .parse()
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Internal transactions are only informative, entries that don't match the expected layout are
+/// skipped instead of failing the whole receipt
+pub(crate) fn deserialize_internal_transactions<'de, D>(
+    deserializer: D,
+) -> Result<Vec<InternalTransaction>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let itxs = match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Array(itxs)) => itxs,
+        None | Some(Value::Null) => return Ok(vec![]),
+        Some(other) => {
+            warn!("Skipping internal transactions, expected a list: {other}");
+            return Ok(vec![]);
+        }
+    };
+    Ok(itxs
+        .into_iter()
+        .filter_map(|itx| match InternalTransaction::deserialize(&itx) {
+            Ok(itx) => Some(itx),
+            Err(e) => {
+                warn!("Skipping internal transaction {itx}: {e}");
+                None
+            }
+        })
+        .collect())
+}
+
+pub(crate) fn deserialize_hex_u256<'de, D>(deserializer: D) -> Result<U256, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) if strip_hex_prefix(&s).is_empty() => Ok(U256::ZERO),
+        StringOrNumber::String(s) => {
+            U256::from_str_radix(strip_hex_prefix(&s), 16).map_err(serde::de::Error::custom)
+        }
+        StringOrNumber::Number(n) => Ok(U256::from(n)),
+    }
+}
+
+pub(crate) fn deserialize_hex_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) if strip_hex_prefix(&s).is_empty() => Ok(0),
+        StringOrNumber::String(s) => {
+            u64::from_str_radix(strip_hex_prefix(&s), 16).map_err(serde::de::Error::custom)
+        }
+        StringOrNumber::Number(n) => Ok(n),
+    }
+}
+
+pub(crate) fn deserialize_hex_bytes<'de, D>(deserializer: D) -> Result<Bytes, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let data = String::deserialize(deserializer)?;
+    let data = strip_hex_prefix(&data);
+    if data.len() % 2 != 0 || !data.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(serde::de::Error::custom("Invalid hex data"));
+    }
+    Ok(Bytes::from(hex_to_bytes(data)))
+}
+
+fn deserialize_number<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) => s.parse().map_err(serde::de::Error::custom),
+        StringOrNumber::Number(n) => Ok(n as usize),
+    }
+}
@@ -1,14 +1,18 @@
-use crate::block::ProcessingEVMBlock;
+use crate::account_history::{AccountHistory, HistoryLookup};
+use crate::address_store::{AddressStore, StoredAddress};
+use crate::block::{collapse_row_deltas, AddressLookups, DecodedRow, ProcessingEVMBlock};
+use crate::resolver::{AddressResolver, HttpResolver, ResolveError};
 use crate::types::evm_types::AccountRow;
-use crate::types::names::EOSIO_EVM;
 use alloy::primitives::Address;
 use antelope::api::client::{APIClient, DefaultProvider};
-use antelope::api::v1::structs::{GetTableRowsParams, IndexPosition, TableIndexType};
 use antelope::chain::name::Name;
 use futures_util::stream::{SplitSink, SplitStream};
+use moka::policy::EvictionPolicy;
 use moka::sync::Cache;
+use serde::{Deserialize, Serialize};
 use std::collections::BinaryHeap;
 use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
@@ -17,22 +21,344 @@ use tracing::info;
 pub type WebsocketTransmitter = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
 pub type WebsocketReceiver = SplitStream<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>>;
 
-pub struct NameToAddressCache {
-    cache: Cache<u64, Address>,
-    index_cache: Cache<u64, Address>,
-    api_client: APIClient<DefaultProvider>,
+/// Where name and index lookups were answered from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LookupStats {
+    /// Account rows and wallet events of the translated blocks, including the account history
+    pub deltas: u64,
+    /// Earlier fallback answers
+    pub cache: u64,
+    /// Fallback resolver, HTTP unless configured otherwise
+    pub http: u64,
 }
 
-impl NameToAddressCache {
+pub const DEFAULT_ADDRESS_CACHE_CAPACITY: u64 = 10_000;
+pub const DEFAULT_PREFETCH_BLOCKS: usize = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheEviction {
+    /// Admits new entries based on how often they are looked up, see moka's TinyLFU
+    #[default]
+    TinyLfu,
+    /// Evicts the least recently looked up entries
+    Lru,
+}
+
+/// Sizing and persistence of the name and index caches, each cache holds up to `capacity`
+/// entries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AddressCacheConfig {
+    pub capacity: u64,
+    pub eviction: CacheEviction,
+    /// File the resolved addresses are written to, and loaded from at startup
+    pub path: Option<String>,
+    /// Most blocks whose lookups are prefetched together ahead of finalization, 0 disables the
+    /// prefetch
+    pub prefetch_blocks: usize,
+}
+
+impl Default for AddressCacheConfig {
+    fn default() -> Self {
+        AddressCacheConfig {
+            capacity: DEFAULT_ADDRESS_CACHE_CAPACITY,
+            eviction: CacheEviction::default(),
+            path: None,
+            prefetch_blocks: DEFAULT_PREFETCH_BLOCKS,
+        }
+    }
+}
+
+fn build_cache(capacity: u64, eviction: CacheEviction) -> Cache<u64, CachedAddress> {
+    let policy = match eviction {
+        CacheEviction::TinyLfu => EvictionPolicy::tiny_lfu(),
+        CacheEviction::Lru => EvictionPolicy::lru(),
+    };
+    Cache::builder()
+        .max_capacity(capacity)
+        .eviction_policy(policy)
+        .build()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CachedAddress {
+    address: Address,
+    from_deltas: bool,
+}
+
+/// Layered resolver: account history, then caches fed by the translated blocks and by earlier
+/// answers, then the fallback resolver
+pub struct NameToAddressCache<R = HttpResolver> {
+    cache: Cache<u64, CachedAddress>,
+    index_cache: Cache<u64, CachedAddress>,
+    fallback: R,
+    history: Option<Mutex<AccountHistory>>,
+    store: Option<AddressStore>,
+    /// Rows removed by the last recorded block, dropped from the caches at the next one
+    pending_removals: Mutex<Vec<AccountRow>>,
+    served_from_deltas: AtomicU64,
+    served_from_cache: AtomicU64,
+    served_from_http: AtomicU64,
+}
+
+impl NameToAddressCache<HttpResolver> {
     pub fn new(api_client: APIClient<DefaultProvider>) -> Self {
+        Self::with_fallback(HttpResolver::new(api_client))
+    }
+}
+
+impl<R: AddressResolver> NameToAddressCache<R> {
+    pub fn with_fallback(fallback: R) -> Self {
+        Self::with_capacity(
+            fallback,
+            DEFAULT_ADDRESS_CACHE_CAPACITY,
+            CacheEviction::default(),
+        )
+    }
+
+    pub fn with_capacity(fallback: R, capacity: u64, eviction: CacheEviction) -> Self {
         NameToAddressCache {
-            cache: Cache::new(10_000),
-            index_cache: Cache::new(10_000),
-            api_client,
+            cache: build_cache(capacity, eviction),
+            index_cache: build_cache(capacity, eviction),
+            fallback,
+            history: None,
+            store: None,
+            pending_removals: Mutex::new(vec![]),
+            served_from_deltas: AtomicU64::new(0),
+            served_from_cache: AtomicU64::new(0),
+            served_from_http: AtomicU64::new(0),
+        }
+    }
+
+    /// Answers lookups as of the block they are made for, the fallback is only used for accounts
+    /// the history has no record of
+    pub fn with_history(mut self, history: AccountHistory) -> Self {
+        self.history = Some(Mutex::new(history));
+        self
+    }
+
+    /// Warms the caches up with the addresses of an earlier run and writes new ones to the store
+    pub fn with_store(mut self, mut store: AddressStore) -> Self {
+        let entries = store.take_entries();
+        for entry in &entries {
+            let cached = |address| CachedAddress {
+                address,
+                from_deltas: false,
+            };
+            // The index of a name entry has an index entry of its own
+            match *entry {
+                StoredAddress::Name { name, address, .. } => {
+                    self.cache.insert(name, cached(address));
+                }
+                StoredAddress::Index { index, address } => {
+                    self.index_cache.insert(index, cached(address));
+                }
+                StoredAddress::NameRemoved { name } => self.cache.invalidate(&name),
+                StoredAddress::IndexRemoved { index } => self.index_cache.invalidate(&index),
+            }
+        }
+        info!("Loaded {} stored addresses", entries.len());
+        self.store = Some(store);
+        self
+    }
+
+    /// Feeds an eosio.evm account table row, rows created from the EVM side have no name
+    pub fn insert_row(&self, row: &AccountRow) {
+        let address = Address::from(row.address.data);
+        let name = row.account.value();
+        if name != 0 {
+            self.insert_name(name, Some(row.index), address, true);
+        }
+        self.insert_index(row.index, address, true);
+    }
+
+    /// Drops the name and index of a removed row, unless a later row gave them another address
+    fn remove_row(&self, row: &AccountRow) {
+        let address = Address::from(row.address.data);
+        let name = row.account.value();
+        let removed = |cached: Option<CachedAddress>| cached.is_some_and(|c| c.address == address);
+        if name != 0 && removed(self.cache.get(&name)) {
+            self.cache.invalidate(&name);
+            self.persist(StoredAddress::NameRemoved { name });
+        }
+        if removed(self.index_cache.get(&row.index)) {
+            self.index_cache.invalidate(&row.index);
+            self.persist(StoredAddress::IndexRemoved { index: row.index });
+        }
+    }
+
+    fn insert_name(&self, name: u64, index: Option<u64>, address: Address, from_deltas: bool) {
+        let previous = self.cache.get(&name);
+        self.cache.insert(
+            name,
+            CachedAddress {
+                address,
+                from_deltas,
+            },
+        );
+        if previous.map(|cached| cached.address) != Some(address) {
+            self.persist(StoredAddress::Name {
+                name,
+                index,
+                address,
+            });
+        }
+    }
+
+    fn insert_index(&self, index: u64, address: Address, from_deltas: bool) {
+        let previous = self.index_cache.get(&index);
+        self.index_cache.insert(
+            index,
+            CachedAddress {
+                address,
+                from_deltas,
+            },
+        );
+        if previous.map(|cached| cached.address) != Some(address) {
+            self.persist(StoredAddress::Index { index, address });
+        }
+    }
+
+    fn persist(&self, entry: StoredAddress) {
+        if let Some(store) = &self.store {
+            store.append(&entry);
+        }
+    }
+
+    fn flush_store(&self) {
+        if let Some(store) = &self.store {
+            store.flush();
+        }
+    }
+
+    /// Fails once persisting the account history or the address store failed
+    pub fn check_writes(&self) -> eyre::Result<()> {
+        if let Some(history) = &self.history {
+            history.lock().unwrap().check_writes()?;
+        }
+        self.store
+            .as_ref()
+            .map_or(Ok(()), AddressStore::check_writes)
+    }
+
+    pub fn stats(&self) -> LookupStats {
+        LookupStats {
+            deltas: self.served_from_deltas.load(Ordering::Relaxed),
+            cache: self.served_from_cache.load(Ordering::Relaxed),
+            http: self.served_from_http.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resolves the lookups not already answered by the history or the caches with batched
+    /// fallback queries, so finalizing the blocks they come from doesn't wait on them.
+    /// Returns how many addresses were fetched.
+    pub async fn prefetch(
+        &self,
+        block_num: u32,
+        lookups: &AddressLookups,
+    ) -> Result<usize, ResolveError> {
+        let (names, indexes) = {
+            let history = self.history.as_ref().map(|history| history.lock().unwrap());
+            let unknown = |lookup: &dyn Fn(&AccountHistory) -> HistoryLookup| {
+                history
+                    .as_ref()
+                    .map_or(true, |history| lookup(history) == HistoryLookup::Unknown)
+            };
+            let names = lookups
+                .names
+                .iter()
+                .copied()
+                .filter(|name| !self.cache.contains_key(name))
+                .filter(|&name| unknown(&|history| history.address_by_name(name, block_num)))
+                .collect::<Vec<_>>();
+            let indexes = lookups
+                .indexes
+                .iter()
+                .copied()
+                .filter(|index| !self.index_cache.contains_key(index))
+                .filter(|&index| unknown(&|history| history.address_by_index(index, block_num)))
+                .collect::<Vec<_>>();
+            (names, indexes)
+        };
+        if names.is_empty() && indexes.is_empty() {
+            return Ok(0);
+        }
+
+        let fetched = |address| CachedAddress {
+            address,
+            from_deltas: false,
+        };
+        let by_name = self.fallback.addresses_by_names(&names, block_num).await?;
+        let by_index = self
+            .fallback
+            .addresses_by_indexes(&indexes, block_num)
+            .await?;
+        // Rows of blocks finalized in the meantime take precedence
+        for (&name, &address) in &by_name {
+            if self
+                .cache
+                .entry(name)
+                .or_insert(fetched(address))
+                .is_fresh()
+            {
+                self.persist(StoredAddress::Name {
+                    name,
+                    index: None,
+                    address,
+                });
+            }
+        }
+        for (&index, &address) in &by_index {
+            if self
+                .index_cache
+                .entry(index)
+                .or_insert(fetched(address))
+                .is_fresh()
+            {
+                self.persist(StoredAddress::Index { index, address });
+            }
         }
+        self.flush_store();
+        Ok(by_name.len() + by_index.len())
     }
 
-    pub async fn get(&self, name: u64) -> Option<Address> {
+    /// `None` if the history is disabled or doesn't know the account
+    fn history_lookup(
+        &self,
+        lookup: impl FnOnce(&AccountHistory) -> HistoryLookup,
+    ) -> Option<Option<Address>> {
+        let address = match lookup(&self.history.as_ref()?.lock().unwrap()) {
+            HistoryLookup::Found(address) => Some(address),
+            HistoryLookup::Absent => None,
+            HistoryLookup::Unknown => return None,
+        };
+        self.served_from_deltas.fetch_add(1, Ordering::Relaxed);
+        Some(address)
+    }
+
+    fn cache_hit(&self, cached: CachedAddress) -> Address {
+        let counter = if cached.from_deltas {
+            &self.served_from_deltas
+        } else {
+            &self.served_from_cache
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached.address
+    }
+}
+
+impl<R: AddressResolver> AddressResolver for NameToAddressCache<R> {
+    async fn address_by_name(
+        &self,
+        name: u64,
+        block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        if let Some(address) =
+            self.history_lookup(|history| history.address_by_name(name, block_num))
+        {
+            return Ok(address);
+        }
         let cached = self.cache.get(&name);
         info!(
             "getting {} cache hit = {:?}",
@@ -40,78 +366,70 @@ impl NameToAddressCache {
             cached.is_some()
         );
         if let Some(cached) = cached {
-            Some(cached)
-        } else {
-            let evm_contract = Name::from_u64(EOSIO_EVM);
-            // TODO: hardcode this in names.rs for performance
-            let account = Name::new_from_str("account");
-            let account_result = self
-                .api_client
-                .v1_chain
-                .get_table_rows::<AccountRow>(GetTableRowsParams {
-                    code: evm_contract,
-                    table: account,
-                    scope: Some(evm_contract),
-                    lower_bound: Some(TableIndexType::UINT64(name)),
-                    upper_bound: Some(TableIndexType::UINT64(name)),
-                    limit: Some(1),
-                    reverse: None,
-                    index_position: Some(IndexPosition::TERTIARY),
-                    show_payer: None,
-                })
-                .await
-                .unwrap();
-            if account_result.rows.is_empty() {
-                info!("Got empty rows for {}", Name::from_u64(name).as_string());
-                return None;
-            }
-
-            let row_index = account_result.rows[0].index;
-            let address_checksum = account_result.rows[0].address;
-            let address = Address::from(address_checksum.data);
-            self.cache.insert(name, address);
-            self.index_cache.insert(row_index, address);
-            Some(address)
+            return Ok(Some(self.cache_hit(cached)));
         }
+        self.served_from_http.fetch_add(1, Ordering::Relaxed);
+        // Absent accounts are not cached, they may be created later on
+        let Some(address) = self.fallback.address_by_name(name, block_num).await? else {
+            return Ok(None);
+        };
+        self.insert_name(name, None, address, false);
+        self.flush_store();
+        Ok(Some(address))
     }
 
-    pub async fn get_index(&self, index: u64) -> Option<Address> {
+    async fn address_by_index(
+        &self,
+        index: u64,
+        block_num: u32,
+    ) -> Result<Option<Address>, ResolveError> {
+        if let Some(address) =
+            self.history_lookup(|history| history.address_by_index(index, block_num))
+        {
+            return Ok(address);
+        }
         let cached = self.index_cache.get(&index);
         info!("getting index {} cache hit = {:?}", index, cached.is_some());
         if let Some(cached) = cached {
-            Some(cached)
-        } else {
-            let evm_contract = Name::from_u64(EOSIO_EVM);
-            // TODO: hardcode this in names.rs for performance
-            let account = Name::new_from_str("account");
-            let account_result = self
-                .api_client
-                .v1_chain
-                .get_table_rows::<AccountRow>(GetTableRowsParams {
-                    code: evm_contract,
-                    table: account,
-                    scope: Some(evm_contract),
-                    lower_bound: Some(TableIndexType::UINT64(index)),
-                    upper_bound: Some(TableIndexType::UINT64(index)),
-                    limit: Some(1),
-                    reverse: None,
-                    index_position: Some(IndexPosition::PRIMARY),
-                    show_payer: None,
-                })
-                .await
-                .unwrap();
-            if account_result.rows.is_empty() {
-                info!("Got empty rows for {}", index);
-                return None;
-            }
+            return Ok(Some(self.cache_hit(cached)));
+        }
+        self.served_from_http.fetch_add(1, Ordering::Relaxed);
+        let Some(address) = self.fallback.address_by_index(index, block_num).await? else {
+            return Ok(None);
+        };
+        self.insert_index(index, address, false);
+        self.flush_store();
+        Ok(Some(address))
+    }
 
-            let row_name = account_result.rows[0].account;
-            let address_checksum = account_result.rows[0].address;
-            let address = Address::from(address_checksum.data);
-            self.cache.insert(row_name.value(), address);
-            self.index_cache.insert(index, address);
-            Some(address)
+    /// Removals take effect at the next block, so lookups for actions of the block, like the
+    /// storage rows of a self destructed contract, still see the removed account
+    fn record_block(&self, block_num: u32, rows: &[DecodedRow]) {
+        let mut pending_removals = self.pending_removals.lock().unwrap();
+        for row in pending_removals.drain(..) {
+            self.remove_row(&row);
         }
+        for row in collapse_row_deltas(rows) {
+            if let DecodedRow::Account { present, row } = row {
+                // A removed row holds the last address of the account
+                self.insert_row(&row);
+                if !present {
+                    pending_removals.push(row);
+                }
+            }
+        }
+        drop(pending_removals);
+        if let Some(history) = &self.history {
+            history.lock().unwrap().record_block(block_num, rows);
+        }
+        self.flush_store();
+        self.fallback.record_block(block_num, rows);
+    }
+
+    fn record_wallet(&self, name: u64, address: Address) {
+        self.insert_name(name, None, address, true);
+        self.flush_store();
+        self.fallback.record_wallet(name, address);
     }
 }
 
@@ -0,0 +1,88 @@
+use alloy::primitives::{Address, U256};
+use antelope::chain::checksum::{Checksum160, Checksum256};
+use antelope::chain::name::Name;
+use telos_translator_rs::account_history::{AccountHistory, HistoryLookup};
+use telos_translator_rs::block::DecodedRow;
+use telos_translator_rs::types::evm_types::AccountRow;
+
+fn address(byte: u8) -> Address {
+    Address::repeat_byte(byte)
+}
+
+fn account(index: u64, name: &str, address: Address, balance: u64, present: bool) -> DecodedRow {
+    DecodedRow::Account {
+        present,
+        row: AccountRow {
+            index,
+            address: Checksum160::from_bytes(address.as_slice()).unwrap(),
+            account: Name::new_from_str(name),
+            nonce: 0,
+            code: vec![],
+            balance: Checksum256::from_bytes(&U256::from(balance).to_be_bytes::<32>()).unwrap(),
+        },
+    }
+}
+
+fn record_blocks(history: &mut AccountHistory) {
+    history.record_block(10, &[account(1, "alice", address(0xaa), 0, true)]);
+    // Balance changes don't change the mappings
+    history.record_block(20, &[account(1, "alice", address(0xaa), 5, true)]);
+    history.record_block(30, &[account(1, "alice", address(0xaa), 5, false)]);
+    history.record_block(40, &[account(2, "alice", address(0xbb), 0, true)]);
+}
+
+fn assert_history(history: &AccountHistory) {
+    let alice = Name::new("alice").value();
+
+    assert_eq!(history.address_by_name(alice, 9), HistoryLookup::Unknown);
+    assert_eq!(
+        history.address_by_name(alice, 10),
+        HistoryLookup::Found(address(0xaa))
+    );
+    // The row is removed by an action of block 30, earlier actions of the block still see it
+    assert_eq!(
+        history.address_by_name(alice, 30),
+        HistoryLookup::Found(address(0xaa))
+    );
+    assert_eq!(history.address_by_name(alice, 31), HistoryLookup::Absent);
+    assert_eq!(
+        history.address_by_name(alice, 40),
+        HistoryLookup::Found(address(0xbb))
+    );
+
+    assert_eq!(
+        history.address_by_index(1, 25),
+        HistoryLookup::Found(address(0xaa))
+    );
+    assert_eq!(history.address_by_index(1, 100), HistoryLookup::Absent);
+    assert_eq!(history.address_by_index(2, 39), HistoryLookup::Unknown);
+    assert_eq!(history.address_by_index(3, 100), HistoryLookup::Unknown);
+    assert_eq!(history.last_block(), Some(40));
+}
+
+#[test]
+fn test_account_history() {
+    let mut history = AccountHistory::default();
+    record_blocks(&mut history);
+    assert_history(&history);
+}
+
+#[test]
+fn test_persisted_history() {
+    let path = std::env::temp_dir().join(format!("account-history-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut history = AccountHistory::open(&path).unwrap();
+    record_blocks(&mut history);
+    drop(history);
+
+    // Blocks translated again after a restart are already recorded
+    let mut reopened = AccountHistory::open(&path).unwrap();
+    assert_history(&reopened);
+    reopened.record_block(10, &[account(1, "alice", address(0xcc), 0, true)]);
+    assert_history(&reopened);
+    drop(reopened);
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -5,6 +5,7 @@ use antelope::{
 };
 use telos_translator_rs::{
     block::ProcessingEVMBlock,
+    chain_spec::TelosChainSpec,
     types::{
         env::{ANTELOPE_EPOCH_MS, ANTELOPE_INTERVAL_MS, MAINNET_DEPLOY_CONFIG},
         ship_types::{
@@ -96,8 +97,15 @@ async fn genesis_mainnet() {
     block.deserialize();
 
     let (header, payload) = block
-        .generate_evm_data(zero_bytes, evm_delta, &native_to_evm_cache)
-        .await;
+        .generate_evm_data(
+            zero_bytes,
+            evm_delta,
+            0,
+            &TelosChainSpec::default(),
+            &native_to_evm_cache,
+        )
+        .await
+        .unwrap();
 
     println!("genesis: {:#?}", header);
     println!("hash: {:#?}", payload.block_hash);
@@ -131,8 +139,15 @@ async fn deploy_mainnet() {
     block.deserialize();
 
     let (header, payload) = block
-        .generate_evm_data(parent_hash, evm_delta, &native_to_evm_cache)
-        .await;
+        .generate_evm_data(
+            parent_hash,
+            evm_delta,
+            0,
+            &TelosChainSpec::default(),
+            &native_to_evm_cache,
+        )
+        .await
+        .unwrap();
 
     println!("genesis: {:#?}", header);
     println!("hash: {:#?}", payload.block_hash);
@@ -0,0 +1,1229 @@
+use alloy::hex;
+use alloy::primitives::{keccak256, Address, FixedBytes, TxKind, U256};
+use alloy_consensus::{SignableTransaction, TxEnvelope, TxLegacy};
+use antelope::api::client::APIClient;
+use antelope::chain::asset::{Asset, Symbol};
+use antelope::chain::checksum::{Checksum160, Checksum256};
+use antelope::chain::name::Name;
+use antelope::chain::varint::VarUint32;
+use antelope::chain::{Encoder, Packer};
+use telos_translator_rs::block::{
+    collapse_row_deltas, decode, AccountDiff, AddressLookups, ContractUpdate, DecodedRow,
+    ProcessingEVMBlock, TranslationError, TranslatorWarning, WalletEvents,
+};
+use telos_translator_rs::chain_spec::TelosChainSpec;
+use telos_translator_rs::resolver::{AddressResolver, MemoryResolver, ResolveError};
+use telos_translator_rs::translator::ChainIdException;
+use telos_translator_rs::types::evm_types::{
+    AccountRow, AccountStateRow, CreateAction, EvmContractConfigRow, OpenWalletAction,
+    PrintedReceiptError, RawAction, TransferAction, WithdrawAction,
+};
+use telos_translator_rs::types::ship_types::{
+    transaction_status, Account, AccountMetadata, AccountMetadataV0, AccountV0, Action,
+    ActionReceipt, ActionReceiptV0, ActionTrace, ActionTraceV0, BlockHeader, BlockPosition, Code,
+    CodeId, CodeV0, ContractRow, ContractRowV0, GetBlocksResultV0, PermissionLevel, Row,
+    SignedBlock, SignedBlockHeader, TableDelta, TableDeltaV0, TransactionTrace, TransactionTraceV0,
+};
+use telos_translator_rs::types::translator_types::{LookupStats, NameToAddressCache};
+
+const BLOCK_NUM: u32 = 100;
+const BLOCK_DELTA: u32 = 36;
+
+fn sender() -> Address {
+    "0xc51fe232a0153f1f44572369cefe7b90f2ba08a5"
+        .parse()
+        .unwrap()
+}
+
+fn raw_action(nonce: u64, estimate_gas: bool) -> RawAction {
+    let tx = TxLegacy {
+        chain_id: None,
+        nonce,
+        gas_price: 0,
+        gas_limit: 21_000,
+        to: TxKind::Call(Address::ZERO),
+        value: U256::from(1),
+        input: Default::default(),
+    };
+    let mut raw = vec![];
+    tx.encode_for_signing(&mut raw);
+
+    RawAction {
+        ram_payer: Name::new("eosio.evm"),
+        tx: raw,
+        estimate_gas,
+        sender: Some(Checksum160::from_bytes(sender().as_slice()).unwrap()),
+    }
+}
+
+fn printed_receipt(trx_index: usize) -> String {
+    format!(
+        "RCPT{{{{{{\"charged_gas\":\"\",\"trx_index\":{trx_index},\"block\":{},\"status\":1,\"epoch\":0,\"createdaddr\":\"\",\"gasused\":\"5208\",\"logs\":[],\"output\":\"\",\"errors\":[],\"itxs\":[]}}}}}}RCPT",
+        BLOCK_NUM - BLOCK_DELTA
+    )
+}
+
+fn raw_action_trace(raw: &RawAction, console: String, global_sequence: u64) -> ActionTrace {
+    action_trace(
+        "eosio.evm",
+        "raw",
+        "eosio.evm",
+        Encoder::pack(raw),
+        console,
+        global_sequence,
+    )
+}
+
+fn action_trace(
+    account: &str,
+    name: &str,
+    receiver: &str,
+    data: Vec<u8>,
+    console: String,
+    global_sequence: u64,
+) -> ActionTrace {
+    ActionTrace::V0(ActionTraceV0 {
+        action_ordinal: VarUint32::new(1),
+        creator_action_ordinal: VarUint32::new(0),
+        receipt: Some(ActionReceipt::V0(ActionReceiptV0 {
+            receiver: Name::new(receiver),
+            global_sequence,
+            ..Default::default()
+        })),
+        receiver: Name::new(receiver),
+        act: Action {
+            account: Name::new(account),
+            name: Name::new(name),
+            authorization: vec![],
+            data,
+        },
+        console,
+        ..Default::default()
+    })
+}
+
+fn transaction_trace(action_traces: Vec<ActionTrace>) -> TransactionTrace {
+    transaction_trace_with_status(transaction_status::EXECUTED, action_traces)
+}
+
+fn transaction_trace_with_status(status: u8, action_traces: Vec<ActionTrace>) -> TransactionTrace {
+    TransactionTrace::V0(TransactionTraceV0 {
+        status,
+        action_traces,
+        ..Default::default()
+    })
+}
+
+fn contract_row<T: Packer>(table: &str, primary_key: u64, present: bool, value: &T) -> Row {
+    scoped_contract_row(table, Name::new("eosio.evm"), primary_key, present, value)
+}
+
+fn scoped_contract_row<T: Packer>(
+    table: &str,
+    scope: Name,
+    primary_key: u64,
+    present: bool,
+    value: &T,
+) -> Row {
+    Row {
+        present,
+        data: Encoder::pack(&ContractRow::V0(ContractRowV0 {
+            code: Name::new("eosio.evm"),
+            scope,
+            table: Name::new(table),
+            primary_key,
+            payer: Name::new("eosio.evm"),
+            value: Encoder::pack(value),
+        })),
+    }
+}
+
+fn contract_row_delta(rows: Vec<Row>) -> TableDelta {
+    TableDelta::V0(TableDeltaV0 {
+        name: "contract_row".to_string(),
+        rows,
+    })
+}
+
+fn checksum256(value: u64) -> Checksum256 {
+    Checksum256::from_bytes(&U256::from(value).to_be_bytes::<32>()).unwrap()
+}
+
+fn tx_nonce(block: &ProcessingEVMBlock, index: usize) -> u64 {
+    match &block.transactions[index].0.envelope {
+        TxEnvelope::Legacy(stx) => stx.tx().nonce,
+        _ => panic!("expected a legacy transaction"),
+    }
+}
+
+fn tx_to(block: &ProcessingEVMBlock, index: usize) -> TxKind {
+    match &block.transactions[index].0.envelope {
+        TxEnvelope::Legacy(stx) => stx.tx().to,
+        _ => panic!("expected a legacy transaction"),
+    }
+}
+
+/// Sender of a translator generated transaction, carried in the `s` value of its signature
+fn tx_padded_sender(block: &ProcessingEVMBlock, index: usize) -> Address {
+    match &block.transactions[index].0.envelope {
+        TxEnvelope::Legacy(stx) => {
+            Address::from_slice(&stx.signature().s().to_be_bytes::<32>()[..20])
+        }
+        _ => panic!("expected a legacy transaction"),
+    }
+}
+
+async fn translate(traces: Vec<TransactionTrace>, deltas: Vec<TableDelta>) -> ProcessingEVMBlock {
+    translate_with_cache(
+        traces,
+        deltas,
+        &NameToAddressCache::new(APIClient::default()),
+    )
+    .await
+}
+
+/// Deserialized block, ready to be translated
+fn processing_block(traces: Vec<TransactionTrace>, deltas: Vec<TableDelta>) -> ProcessingEVMBlock {
+    let signed_block = SignedBlock {
+        header: SignedBlockHeader {
+            header: BlockHeader::default(),
+            // Zeroed K1 signature
+            producer_signature: decode(&[0u8; 66]),
+        },
+        transactions: vec![],
+        block_extensions: vec![],
+    };
+    let block_pos = BlockPosition {
+        block_num: BLOCK_NUM,
+        block_id: Checksum256::default(),
+    };
+
+    let mut block = ProcessingEVMBlock::new(
+        40,
+        BLOCK_NUM,
+        block_pos.block_id,
+        BLOCK_NUM,
+        block_pos.block_id,
+        GetBlocksResultV0 {
+            head: block_pos.clone(),
+            last_irreversible: block_pos.clone(),
+            this_block: Some(block_pos.clone()),
+            prev_block: None,
+            block: Some(Encoder::pack(&signed_block)),
+            traces: Some(Encoder::pack(&traces)),
+            deltas: Some(Encoder::pack(&deltas)),
+        },
+    );
+    block.deserialize();
+    block
+}
+
+async fn translate_with_cache(
+    traces: Vec<TransactionTrace>,
+    deltas: Vec<TableDelta>,
+    native_to_evm_cache: &NameToAddressCache,
+) -> ProcessingEVMBlock {
+    let mut block = processing_block(traces, deltas);
+    block
+        .generate_evm_data(
+            FixedBytes::default(),
+            BLOCK_DELTA,
+            0,
+            &TelosChainSpec::default(),
+            native_to_evm_cache,
+        )
+        .await
+        .unwrap();
+    block
+}
+
+#[tokio::test]
+async fn test_skip_gas_estimation() {
+    let estimation = raw_action(0, true);
+    let transfer = raw_action(0, false);
+
+    let block = translate(
+        vec![
+            transaction_trace(vec![raw_action_trace(&estimation, String::new(), 1)]),
+            transaction_trace(vec![raw_action_trace(&transfer, printed_receipt(0), 2)]),
+        ],
+        vec![],
+    )
+    .await;
+
+    assert_eq!(block.transactions.len(), 1);
+    assert_eq!(
+        block.warnings,
+        vec![TranslatorWarning::GasEstimation {
+            block_num: BLOCK_NUM,
+            raw_tx_hash: keccak256(&estimation.tx),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_missing_printed_receipt() {
+    let transfer = raw_action(0, false);
+    let mut block = processing_block(
+        vec![transaction_trace(vec![raw_action_trace(
+            &transfer,
+            String::from("no receipt here"),
+            1,
+        )])],
+        vec![],
+    );
+    let result = block
+        .generate_evm_data(
+            FixedBytes::default(),
+            BLOCK_DELTA,
+            0,
+            &TelosChainSpec::default(),
+            &MemoryResolver::new(),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(TranslationError::PrintedReceipt {
+            block_num: BLOCK_NUM,
+            error: PrintedReceiptError::NotFound,
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_skip_failed_native_transactions() {
+    let mut failed_action = raw_action_trace(&raw_action(1, false), printed_receipt(1), 3);
+    if let ActionTrace::V0(trace) = &mut failed_action {
+        trace.receipt = None;
+        trace.except = Some("eosio_assert_message assertion failure".to_string());
+    }
+
+    let failed_dtrx = transaction_trace_with_status(
+        transaction_status::HARD_FAIL,
+        vec![raw_action_trace(
+            &raw_action(2, false),
+            printed_receipt(2),
+            4,
+        )],
+    );
+    let onerror = TransactionTrace::V0(TransactionTraceV0 {
+        status: transaction_status::EXECUTED,
+        failed_dtrx_trace: Some(Box::new(failed_dtrx)),
+        ..Default::default()
+    });
+
+    let block = translate(
+        vec![
+            transaction_trace_with_status(
+                transaction_status::HARD_FAIL,
+                vec![raw_action_trace(
+                    &raw_action(3, false),
+                    printed_receipt(3),
+                    5,
+                )],
+            ),
+            transaction_trace_with_status(
+                transaction_status::SOFT_FAIL,
+                vec![raw_action_trace(
+                    &raw_action(4, false),
+                    printed_receipt(4),
+                    6,
+                )],
+            ),
+            transaction_trace(vec![failed_action]),
+            onerror,
+            transaction_trace(vec![raw_action_trace(
+                &raw_action(0, false),
+                printed_receipt(0),
+                7,
+            )]),
+        ],
+        vec![],
+    )
+    .await;
+
+    assert_eq!(block.transactions.len(), 1);
+    assert_eq!(tx_nonce(&block, 0), 0);
+    assert_eq!(block.skipped_transactions, 3);
+    assert_eq!(block.skipped_actions, 1);
+}
+
+#[tokio::test]
+async fn test_order_by_global_sequence() {
+    // Notification executed first but listed last in the trace
+    let block = translate(
+        vec![transaction_trace(vec![
+            raw_action_trace(&raw_action(1, false), printed_receipt(1), 11),
+            raw_action_trace(&raw_action(0, false), printed_receipt(0), 10),
+        ])],
+        vec![],
+    )
+    .await;
+
+    assert_eq!(block.transactions.len(), 2);
+    assert_eq!(tx_nonce(&block, 0), 0);
+    assert_eq!(tx_nonce(&block, 1), 1);
+    assert!(block.warnings.is_empty());
+}
+
+#[tokio::test]
+async fn test_trx_index_mismatch() {
+    let block = translate(
+        vec![transaction_trace(vec![raw_action_trace(
+            &raw_action(0, false),
+            printed_receipt(3),
+            10,
+        )])],
+        vec![],
+    )
+    .await;
+
+    assert_eq!(block.transactions.len(), 1);
+    assert_eq!(
+        block.warnings,
+        vec![TranslatorWarning::TrxIndexMismatch {
+            block_num: BLOCK_NUM,
+            printed: 3,
+            translated: 0,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_printed_created_address_mismatch() {
+    // Contract address printed for a plain call
+    let printed = sender().create(0);
+    let console = printed_receipt(0).replace(
+        "\"createdaddr\":\"\"",
+        &format!("\"createdaddr\":\"{}\"", hex::encode(printed)),
+    );
+    let block = translate(
+        vec![transaction_trace(vec![raw_action_trace(
+            &raw_action(0, false),
+            console,
+            1,
+        )])],
+        vec![],
+    )
+    .await;
+
+    assert_eq!(block.transactions.len(), 1);
+    assert_eq!(block.transactions[0].0.created_address, Some(printed));
+    assert_eq!(
+        block.warnings,
+        vec![TranslatorWarning::PrintedCreatedAddressMismatch {
+            block_num: BLOCK_NUM,
+            tx_hash: *block.transactions[0].0.hash(),
+            printed,
+            expected: None,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_removed_rows() {
+    // Slot written in a previous block and cleared with SSTORE 0 in this one
+    let cleared_slot = AccountStateRow {
+        index: 5,
+        key: checksum256(1),
+        value: checksum256(42),
+    };
+    let updated_slot = AccountStateRow {
+        index: 5,
+        key: checksum256(2),
+        value: checksum256(7),
+    };
+    let removed_account = AccountRow {
+        index: 6,
+        address: Checksum160::from_bytes(sender().as_slice()).unwrap(),
+        account: Name::new("eosio.evm"),
+        nonce: 3,
+        code: vec![0x60, 0x80],
+        balance: checksum256(1000),
+    };
+
+    let block = translate(
+        vec![],
+        vec![contract_row_delta(vec![
+            scoped_contract_row("accountstate", Name::from_u64(5), 1, false, &cleared_slot),
+            scoped_contract_row("accountstate", Name::from_u64(5), 2, true, &updated_slot),
+            contract_row("account", 6, false, &removed_account),
+        ])],
+    )
+    .await;
+
+    // Storage rows are resolved through the scope of their account
+    let contract = Address::repeat_byte(0x11);
+    let resolver = MemoryResolver::new();
+    resolver.insert(Name::new("contract").value(), 5, contract);
+    let diffs = block.state_diffs(&resolver).await.unwrap();
+
+    let state_diffs = &diffs.account_states;
+    assert_eq!(state_diffs.len(), 2);
+    assert!(state_diffs.iter().all(|diff| diff.address == contract));
+    assert_eq!(state_diffs[0].key, U256::from(1));
+    assert_eq!(state_diffs[0].value, U256::ZERO);
+    assert_eq!(state_diffs[1].key, U256::from(2));
+    assert_eq!(state_diffs[1].value, U256::from(7));
+
+    assert_eq!(diffs.accounts.len(), 1);
+    match &diffs.accounts[0] {
+        AccountDiff::Removed { address, account } => {
+            assert_eq!(*address, sender());
+            assert_eq!(*account, Name::new("eosio.evm"));
+        }
+        AccountDiff::Updated(_) => panic!("expected the account removal"),
+    }
+    assert_eq!(diffs.removed_accounts(), vec![sender()]);
+
+    // The engine API has no removal flag, the account is sent empty
+    let table_row = diffs.accounts[0].to_table_row();
+    assert_eq!(table_row.nonce, 0);
+    assert!(table_row.code.is_empty());
+    assert_eq!(table_row.balance, U256::ZERO);
+
+    // Storage of an account missing from the resolver fails the diffs
+    assert!(matches!(
+        block.state_diffs(&MemoryResolver::new()).await,
+        Err(ResolveError::UnknownIndex {
+            index: 5,
+            block_num: BLOCK_NUM
+        })
+    ));
+
+    // Self destruct: the account row and its storage rows are removed together, the storage is
+    // resolved through the removed row without asking the resolver
+    let destructed_slot = AccountStateRow {
+        index: 1,
+        key: checksum256(3),
+        value: checksum256(9),
+    };
+    let block = translate(
+        vec![],
+        vec![contract_row_delta(vec![
+            scoped_contract_row(
+                "accountstate",
+                Name::from_u64(6),
+                1,
+                false,
+                &destructed_slot,
+            ),
+            contract_row("account", 6, false, &removed_account),
+        ])],
+    )
+    .await;
+    let diffs = block.state_diffs(&MemoryResolver::new()).await.unwrap();
+    assert_eq!(diffs.account_states.len(), 1);
+    assert_eq!(diffs.account_states[0].address, sender());
+    assert_eq!(diffs.account_states[0].key, U256::from(3));
+    assert_eq!(diffs.account_states[0].value, U256::ZERO);
+    assert_eq!(diffs.removed_accounts(), vec![sender()]);
+}
+
+#[tokio::test]
+async fn test_collapse_row_deltas() {
+    // Same slot key in two accounts, row primary keys restart in every scope
+    let slot = |key: u64, value: u64| AccountStateRow {
+        index: key,
+        key: checksum256(key),
+        value: checksum256(value),
+    };
+    let account_slot = |account_index: u64, key: u64, value: u64, present: bool| {
+        scoped_contract_row(
+            "accountstate",
+            Name::from_u64(account_index),
+            key,
+            present,
+            &slot(key, value),
+        )
+    };
+    let account = |nonce: u64| AccountRow {
+        index: 6,
+        address: Checksum160::from_bytes(sender().as_slice()).unwrap(),
+        account: Name::new("eosio.evm"),
+        nonce,
+        code: vec![],
+        balance: checksum256(1000),
+    };
+
+    let block = translate(
+        vec![],
+        vec![contract_row_delta(vec![
+            account_slot(6, 1, 10, true),
+            contract_row("account", 6, true, &account(1)),
+            account_slot(6, 2, 20, true),
+            account_slot(7, 1, 30, true),
+            account_slot(6, 1, 11, true),
+            contract_row("account", 6, true, &account(2)),
+            account_slot(6, 1, 11, false),
+        ])],
+    )
+    .await;
+
+    assert_eq!(block.decoded_rows.len(), 7);
+    let collapsed = collapse_row_deltas(&block.decoded_rows);
+    assert_eq!(collapsed.len(), 4);
+
+    match &collapsed[0] {
+        DecodedRow::AccountState {
+            present,
+            account_index,
+            row,
+        } => {
+            assert!(*present);
+            assert_eq!(*account_index, 6);
+            assert_eq!(row.key.data, checksum256(2).data);
+        }
+        _ => panic!("expected the slot 2 write first"),
+    }
+    match &collapsed[1] {
+        DecodedRow::AccountState {
+            present,
+            account_index,
+            row,
+        } => {
+            assert!(*present);
+            assert_eq!(*account_index, 7);
+            assert_eq!(row.value.data, checksum256(30).data);
+        }
+        _ => panic!("expected the slot 1 write of the other account"),
+    }
+    match &collapsed[2] {
+        DecodedRow::Account { present, row } => {
+            assert!(*present);
+            assert_eq!(row.nonce, 2);
+        }
+        _ => panic!("expected the last account write"),
+    }
+    match &collapsed[3] {
+        DecodedRow::AccountState {
+            present,
+            account_index,
+            row,
+        } => {
+            assert!(!*present);
+            assert_eq!(*account_index, 6);
+            assert_eq!(row.key.data, checksum256(1).data);
+        }
+        _ => panic!("expected the slot 1 removal last"),
+    }
+}
+
+#[tokio::test]
+async fn test_chain_id_violations() {
+    // Transaction signed for chain id 41 while the block is translated with chain id 40
+    let signed = RawAction {
+        ram_payer: Name::new("eosio.evm"),
+        tx: hex::decode("f86780857a307efa808254449427fb069fe2b7735767a601b699638357402af961830138d58076a09767d2e014c69b60890ef5eb81dd170391177f5348afbb5467535780f5b69e07a0024f2570e9f99d175df71ccb2443927c2e34ce40b2990cdba2067b725bee0fbb").unwrap(),
+        estimate_gas: false,
+        sender: None,
+    };
+    let block = translate(
+        vec![transaction_trace(vec![
+            raw_action_trace(&signed, printed_receipt(0), 1),
+            raw_action_trace(&raw_action(0, false), printed_receipt(1), 2),
+        ])],
+        vec![],
+    )
+    .await;
+
+    assert_eq!(block.transactions.len(), 2);
+    assert_eq!(
+        block.chain_id_violations(&[]),
+        vec![TranslatorWarning::ChainIdMismatch {
+            block_num: BLOCK_NUM,
+            tx_hash: "0x86356574dbe4e34a329cc540686624d885b125ca8c3ea7524cddce49584f2c6f"
+                .parse()
+                .unwrap(),
+            chain_id: Some(41),
+        }]
+    );
+
+    // Unsigned transactions are never reported, pre EIP-155 exception doesn't cover chain id 41
+    assert_eq!(
+        block
+            .chain_id_violations(&[ChainIdException::PreEip155])
+            .len(),
+        1
+    );
+    assert!(block
+        .chain_id_violations(&[ChainIdException::BlockRange {
+            start_block: BLOCK_NUM,
+            end_block: BLOCK_NUM,
+        }])
+        .is_empty());
+
+    assert!(ChainIdException::PreEip155.allows(BLOCK_NUM, None));
+    assert!(!ChainIdException::BlockRange {
+        start_block: 0,
+        end_block: BLOCK_NUM - 1,
+    }
+    .allows(BLOCK_NUM, Some(41)));
+}
+
+#[tokio::test]
+async fn test_config_row_audit() {
+    let traces = || {
+        vec![transaction_trace(vec![
+            raw_action_trace(&raw_action(0, false), printed_receipt(0), 1),
+            raw_action_trace(&raw_action(1, false), printed_receipt(1), 2),
+        ])]
+    };
+    let config_row = |last_block: u32, trx_index: u32, gas_used: u64| {
+        contract_row_delta(vec![contract_row(
+            "config",
+            0,
+            true,
+            &EvmContractConfigRow {
+                trx_index,
+                last_block,
+                gas_used_block: checksum256(gas_used),
+                ..Default::default()
+            },
+        )])
+    };
+
+    let block = translate(traces(), vec![config_row(BLOCK_NUM, 2, 42_000)]).await;
+    assert_eq!(block.config_row_mismatch(), None);
+
+    // Counters left from a previous block can't be compared
+    let block = translate(traces(), vec![config_row(BLOCK_NUM - 1, 1, 21_000)]).await;
+    assert_eq!(block.config_row_mismatch(), None);
+
+    let block = translate(traces(), vec![config_row(BLOCK_NUM, 2, 63_000)]).await;
+    assert_eq!(
+        block.config_row_mismatch(),
+        Some(TranslatorWarning::ConfigRowMismatch {
+            block_num: BLOCK_NUM,
+            gas_used: 42_000,
+            contract_gas_used: U256::from(63_000),
+            transactions: 2,
+            contract_trx_index: 2,
+        })
+    );
+
+    let block = translate(traces(), vec![config_row(BLOCK_NUM, 3, 42_000)]).await;
+    assert!(block.config_row_mismatch().is_some());
+
+    // Deposits are not counted and the last config row of the block is the one compared
+    let deposit = TransferAction {
+        from: Name::new("depositor"),
+        to: Name::new("eosio.evm"),
+        quantity: Asset::new(10_000, Symbol::new("TLOS", 4)),
+        memo: format!("{:?}", sender()),
+    };
+    let mut traces = traces();
+    traces.push(transaction_trace(vec![action_trace(
+        "eosio.token",
+        "transfer",
+        "eosio.evm",
+        Encoder::pack(&deposit),
+        String::new(),
+        3,
+    )]));
+    let mut rows = config_row(BLOCK_NUM, 1, 21_000);
+    let TableDelta::V0(last) = config_row(BLOCK_NUM, 2, 42_000);
+    let TableDelta::V0(delta) = &mut rows;
+    delta.rows.extend(last.rows);
+    let block = translate(traces, vec![rows]).await;
+    assert_eq!(block.transactions.len(), 3);
+    assert_eq!(block.config_row_mismatch(), None);
+}
+
+#[tokio::test]
+async fn test_cache_from_deltas() {
+    let withdrawer: Address = "0xb4b01216a5bc8f1c8a33cd990a1239030e60c905"
+        .parse()
+        .unwrap();
+    let depositor = sender();
+
+    let withdraw = WithdrawAction {
+        to: Name::new("withdrawer"),
+        quantity: Asset::new(10_000, Symbol::new("TLOS", 4)),
+    };
+    let open_wallet = OpenWalletAction {
+        account: Name::new("depositor"),
+        address: Checksum160::from_bytes(depositor.as_slice()).unwrap(),
+    };
+    let deposit = TransferAction {
+        from: Name::new("depositor"),
+        to: Name::new("eosio.evm"),
+        quantity: Asset::new(10_000, Symbol::new("TLOS", 4)),
+        memo: String::new(),
+    };
+    let withdrawer_row = AccountRow {
+        index: 4,
+        address: Checksum160::from_bytes(withdrawer.as_slice()).unwrap(),
+        account: Name::new("withdrawer"),
+        nonce: 0,
+        code: vec![],
+        balance: checksum256(0),
+    };
+
+    // Both lookups would panic trying to reach the default http endpoint
+    let cache = NameToAddressCache::new(APIClient::default());
+    let block = translate_with_cache(
+        vec![transaction_trace(vec![
+            action_trace(
+                "eosio.evm",
+                "withdraw",
+                "eosio.evm",
+                Encoder::pack(&withdraw),
+                String::new(),
+                1,
+            ),
+            action_trace(
+                "eosio.evm",
+                "openwallet",
+                "eosio.evm",
+                Encoder::pack(&open_wallet),
+                String::new(),
+                2,
+            ),
+            action_trace(
+                "eosio.token",
+                "transfer",
+                "eosio.evm",
+                Encoder::pack(&deposit),
+                String::new(),
+                3,
+            ),
+        ])],
+        vec![contract_row_delta(vec![contract_row(
+            "account",
+            4,
+            true,
+            &withdrawer_row,
+        )])],
+        &cache,
+    )
+    .await;
+
+    assert_eq!(block.transactions.len(), 2);
+    assert_eq!(tx_padded_sender(&block, 0), withdrawer);
+    assert_eq!(tx_to(&block, 1), TxKind::Call(depositor));
+    assert_eq!(
+        cache.address_by_index(4, BLOCK_NUM).await.unwrap(),
+        Some(withdrawer)
+    );
+    assert_eq!(
+        cache.stats(),
+        LookupStats {
+            deltas: 3,
+            cache: 0,
+            http: 0
+        }
+    );
+}
+
+#[test]
+fn test_address_lookups() {
+    let transfer = |from: &str, memo: &str, global_sequence| {
+        let deposit = TransferAction {
+            from: Name::new(from),
+            to: Name::new("eosio.evm"),
+            quantity: Asset::new(10_000, Symbol::new("TLOS", 4)),
+            memo: memo.to_string(),
+        };
+        action_trace(
+            "eosio.token",
+            "transfer",
+            "eosio.evm",
+            Encoder::pack(&deposit),
+            String::new(),
+            global_sequence,
+        )
+    };
+    let withdraw = |to: &str, global_sequence| {
+        let withdraw = WithdrawAction {
+            to: Name::new(to),
+            quantity: Asset::new(10_000, Symbol::new("TLOS", 4)),
+        };
+        action_trace(
+            "eosio.evm",
+            "withdraw",
+            "eosio.evm",
+            Encoder::pack(&withdraw),
+            String::new(),
+            global_sequence,
+        )
+    };
+    // Created addresses are derived, not looked up
+    let create = CreateAction {
+        account: Name::new("creator"),
+        data: String::new(),
+    };
+    let open_wallet = OpenWalletAction {
+        account: Name::new("opener"),
+        address: Checksum160::from_bytes(sender().as_slice()).unwrap(),
+    };
+    let withdrawer_row = AccountRow {
+        index: 4,
+        address: Checksum160::from_bytes(Address::repeat_byte(4).as_slice()).unwrap(),
+        account: Name::new("withdrawer"),
+        nonce: 0,
+        code: vec![],
+        balance: checksum256(0),
+    };
+    let slot = AccountStateRow {
+        index: 0,
+        key: checksum256(1),
+        value: checksum256(1),
+    };
+
+    let block = processing_block(
+        vec![
+            transaction_trace(vec![
+                transfer("depositor", "", 1),
+                // Memo carries the address, nothing to resolve
+                transfer("memodepositr", &format!("{:?}", sender()), 2),
+                transfer("opener", "", 3),
+                withdraw("alice", 4),
+                withdraw("withdrawer", 5),
+                action_trace(
+                    "eosio.evm",
+                    "create",
+                    "eosio.evm",
+                    Encoder::pack(&create),
+                    String::new(),
+                    6,
+                ),
+                action_trace(
+                    "eosio.evm",
+                    "openwallet",
+                    "eosio.evm",
+                    Encoder::pack(&open_wallet),
+                    String::new(),
+                    7,
+                ),
+            ]),
+            transaction_trace_with_status(
+                transaction_status::HARD_FAIL,
+                vec![withdraw("failed", 8)],
+            ),
+        ],
+        vec![contract_row_delta(vec![
+            contract_row("account", 4, true, &withdrawer_row),
+            scoped_contract_row("accountstate", Name::from_u64(4), 0, true, &slot),
+            scoped_contract_row("accountstate", Name::from_u64(7), 0, true, &slot),
+        ])],
+    );
+
+    let linked = block.linked_accounts();
+    assert_eq!(
+        linked,
+        AddressLookups {
+            names: [Name::new("withdrawer").value(), Name::new("opener").value()].into(),
+            indexes: [4].into(),
+        }
+    );
+    assert_eq!(
+        block.address_lookups(&linked),
+        AddressLookups {
+            names: [Name::new("depositor").value(), Name::new("alice").value(),].into(),
+            indexes: [7].into(),
+        }
+    );
+
+    // Accounts linked by an earlier block of the same batch are not looked up either
+    let next = processing_block(
+        vec![transaction_trace(vec![
+            withdraw("alice", 9),
+            transfer("opener", "", 10),
+        ])],
+        vec![contract_row_delta(vec![scoped_contract_row(
+            "accountstate",
+            Name::from_u64(4),
+            0,
+            true,
+            &slot,
+        )])],
+    );
+    let mut batch_linked = linked;
+    batch_linked.extend(next.linked_accounts());
+    assert_eq!(
+        next.address_lookups(&batch_linked),
+        AddressLookups {
+            names: [Name::new("alice").value()].into(),
+            indexes: [].into(),
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_create_address_mismatches() {
+    let create = |account: &str, global_sequence| {
+        let create = CreateAction {
+            account: Name::new(account),
+            data: "salt".to_string(),
+        };
+        action_trace(
+            "eosio.evm",
+            "create",
+            "eosio.evm",
+            Encoder::pack(&create),
+            String::new(),
+            global_sequence,
+        )
+    };
+    let row = |index, account: &str, address: Address| AccountRow {
+        index,
+        address: Checksum160::from_bytes(address.as_slice()).unwrap(),
+        account: Name::new(account),
+        nonce: 0,
+        code: vec![],
+        balance: checksum256(0),
+    };
+    let derived = |account: &str| {
+        CreateAction {
+            account: Name::new(account),
+            data: "salt".to_string(),
+        }
+        .address()
+    };
+    assert_ne!(derived("matching"), derived("mismatching"));
+    // Computed outside this crate: keccak256 of the RLP list ce 88 91b286ba6c000000 84 73616c74,
+    // `matching` as a u64 and `salt`. Not a mainnet create, it only pins the encoding and hash.
+    assert_eq!(
+        derived("matching"),
+        "0x011a33781cf57a6e297db93545df043de6860b04"
+            .parse::<Address>()
+            .unwrap()
+    );
+
+    let block = translate(
+        vec![transaction_trace(vec![
+            create("matching", 1),
+            create("mismatching", 2),
+            create("norow", 3),
+        ])],
+        vec![contract_row_delta(vec![
+            contract_row("account", 1, true, &row(1, "matching", derived("matching"))),
+            contract_row(
+                "account",
+                2,
+                true,
+                &row(2, "mismatching", Address::repeat_byte(2)),
+            ),
+        ])],
+    )
+    .await;
+
+    assert_eq!(
+        block.create_address_mismatches(),
+        vec![TranslatorWarning::CreateAddressMismatch {
+            block_num: BLOCK_NUM,
+            account: Name::new("mismatching"),
+            derived: derived("mismatching"),
+            table: Address::repeat_byte(2),
+        }]
+    );
+
+    // Addresses sent to the engine come from the table rows, derived only without a row
+    let created: Vec<Address> = block
+        .new_wallets
+        .iter()
+        .map(|wallet| match wallet {
+            WalletEvents::CreateWallet(_, create_action) => block.created_address(create_action),
+            _ => panic!("Unexpected wallet event"),
+        })
+        .collect();
+    assert_eq!(
+        created,
+        vec![
+            derived("matching"),
+            Address::repeat_byte(2),
+            derived("norow")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_native_origin() {
+    let with_origin = |trace: ActionTrace, actor: &str, action_ordinal| match trace {
+        ActionTrace::V0(mut trace) => {
+            trace.action_ordinal = VarUint32::new(action_ordinal);
+            trace.act.authorization = vec![PermissionLevel {
+                actor: Name::new(actor),
+                permission: Name::new("active"),
+            }];
+            ActionTrace::V0(trace)
+        }
+        trace => trace,
+    };
+    let deposit = TransferAction {
+        from: Name::new("depositor"),
+        to: Name::new("eosio.evm"),
+        quantity: Asset::new(10_000, Symbol::new("TLOS", 4)),
+        memo: format!("{:?}", sender()),
+    };
+    let native_trx = |id, action_traces| {
+        TransactionTrace::V0(TransactionTraceV0 {
+            id: checksum256(id),
+            status: transaction_status::EXECUTED,
+            action_traces,
+            ..Default::default()
+        })
+    };
+
+    let block = translate(
+        vec![
+            native_trx(
+                1,
+                vec![with_origin(
+                    raw_action_trace(&raw_action(0, false), printed_receipt(0), 20),
+                    "rawsender",
+                    2,
+                )],
+            ),
+            native_trx(
+                2,
+                vec![with_origin(
+                    action_trace(
+                        "eosio.token",
+                        "transfer",
+                        "eosio.evm",
+                        Encoder::pack(&deposit),
+                        String::new(),
+                        10,
+                    ),
+                    "depositor",
+                    3,
+                )],
+            ),
+        ],
+        vec![],
+    )
+    .await;
+
+    // Ordered by global sequence, the deposit comes first
+    let origins = block
+        .transactions
+        .iter()
+        .map(|(transaction, _receipt)| transaction.native_origin.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(origins.len(), 2);
+    // Only raw transactions advance the printed trx_index, the deposit before it doesn't
+    assert!(block.warnings.is_empty());
+
+    assert_eq!(origins[0].trx_id, FixedBytes::from(checksum256(2).data));
+    assert_eq!(origins[0].action_ordinal, 3);
+    assert_eq!(origins[0].global_sequence, 10);
+    assert_eq!(origins[0].authorization[0].actor, Name::new("depositor"));
+    assert_eq!(origins[0].ram_payer, None);
+
+    assert_eq!(origins[1].trx_id, FixedBytes::from(checksum256(1).data));
+    assert_eq!(origins[1].action_ordinal, 2);
+    assert_eq!(origins[1].global_sequence, 20);
+    assert_eq!(origins[1].authorization[0].actor, Name::new("rawsender"));
+    assert_eq!(origins[1].ram_payer, Some(Name::new("eosio.evm")));
+}
+
+#[tokio::test]
+async fn test_unexpected_deposit_symbol() {
+    let transfer = TransferAction {
+        from: Name::new("depositor"),
+        to: Name::new("eosio.evm"),
+        quantity: Asset::new(10_000, Symbol::new("EOS", 4)),
+        memo: format!("{:?}", sender()),
+    };
+    let block = translate(
+        vec![transaction_trace(vec![action_trace(
+            "eosio.token",
+            "transfer",
+            "eosio.evm",
+            Encoder::pack(&transfer),
+            String::new(),
+            1,
+        )])],
+        vec![],
+    )
+    .await;
+
+    assert!(block.transactions.is_empty());
+    assert_eq!(
+        block.warnings,
+        vec![TranslatorWarning::UnexpectedDepositSymbol {
+            block_num: BLOCK_NUM,
+            from: Name::new("depositor"),
+        }]
+    );
+}
+
+#[test]
+fn test_contract_update() {
+    let delta = |name: &str, rows: Vec<Row>| {
+        TableDelta::V0(TableDeltaV0 {
+            name: name.to_string(),
+            rows,
+        })
+    };
+    let row = |data: Vec<u8>| Row {
+        present: true,
+        data,
+    };
+    let metadata = |name: &str, code_hash: u64| {
+        Encoder::pack(&AccountMetadata::V0(AccountMetadataV0 {
+            name: Name::new(name),
+            privileged: false,
+            last_code_update: 0,
+            code: Some(CodeId {
+                vm_type: 0,
+                vm_version: 0,
+                code_hash: checksum256(code_hash),
+            }),
+        }))
+    };
+    let code = |code_hash: u64, code: Vec<u8>| {
+        Encoder::pack(&Code::V0(CodeV0 {
+            vm_type: 0,
+            vm_version: 0,
+            code_hash: checksum256(code_hash),
+            code,
+        }))
+    };
+    let abi = Encoder::pack(&Account::V0(AccountV0 {
+        name: Name::new("eosio.evm"),
+        creation_date: 0,
+        abi: vec![1, 2, 3],
+    }));
+
+    let block = processing_block(
+        vec![],
+        vec![
+            delta("account", vec![row(abi)]),
+            delta(
+                "account_metadata",
+                vec![
+                    row(metadata("eosio.token", 1)),
+                    row(metadata("eosio.evm", 2)),
+                ],
+            ),
+            delta(
+                "code",
+                vec![row(code(1, vec![0xaa])), row(code(2, vec![0xbb, 0xcc]))],
+            ),
+        ],
+    );
+    assert_eq!(
+        block.contract_update,
+        Some(ContractUpdate {
+            code_hash: Some(FixedBytes::from(checksum256(2).data)),
+            code: Some(vec![0xbb, 0xcc].into()),
+            abi: Some(vec![1, 2, 3].into()),
+        })
+    );
+
+    // Only other accounts changed, their rows are not decoded: the account row stops after
+    // the name and the last code row is too short to hold a hash
+    let other_account = [&[0][..], &Name::new("eosio.token").value().to_le_bytes()].concat();
+    let block = processing_block(
+        vec![],
+        vec![
+            delta("account", vec![row(other_account)]),
+            delta("account_metadata", vec![row(metadata("eosio.token", 1))]),
+            delta("code", vec![row(code(1, vec![0xaa])), row(vec![0, 0, 0])]),
+        ],
+    );
+    assert_eq!(block.contract_update, None);
+}
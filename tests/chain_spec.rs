@@ -0,0 +1,113 @@
+use alloy::primitives::Address;
+use antelope::chain::checksum::Checksum256;
+use telos_translator_rs::chain_spec::{
+    HardforkCondition, TelosChainSpec, TelosHardfork, TelosRules, TelosRulesOverride,
+};
+use telos_translator_rs::transaction::make_unique_vrs;
+
+const SPEC: &str = r#"
+[base]
+bad_sig_padding = true
+
+[[hardforks]]
+name = "sig-padding-fix"
+activation = { block = 1000 }
+rules = { bad_sig_padding = false }
+
+[[hardforks]]
+name = "eip1559"
+activation = { revision = 1 }
+rules = { allowed_tx_types = [0, 2], minimum_fee_per_gas = 100 }
+"#;
+
+#[test]
+fn test_rules_schedule() {
+    let spec: TelosChainSpec = toml::from_str(SPEC).unwrap();
+
+    let base = spec.rules_at(999, 0);
+    assert!(base.bad_sig_padding);
+    assert_eq!(base.gas_limit, 0x7fffffff);
+    assert_eq!(base.minimum_fee_per_gas, 7);
+
+    let fixed = spec.rules_at(1000, 0);
+    assert!(!fixed.bad_sig_padding);
+    assert_eq!(fixed.minimum_fee_per_gas, 7);
+
+    // Hardforks only change the rules they name, the others keep their earlier value
+    let eip1559 = spec.rules_at(10, 1);
+    assert!(eip1559.bad_sig_padding);
+    assert_eq!(eip1559.minimum_fee_per_gas, 100);
+    assert_eq!(eip1559.gas_limit, 0x7fffffff);
+
+    let both = spec.rules_at(2000, 2);
+    assert!(!both.bad_sig_padding);
+    assert_eq!(both.minimum_fee_per_gas, 100);
+}
+
+#[test]
+fn test_activation_order() {
+    // The same rule changed twice, the later hardfork wins once both are active
+    let spec: TelosChainSpec = toml::from_str(
+        r#"
+[[hardforks]]
+name = "first"
+activation = { block = 10 }
+rules = { gas_limit = 100 }
+
+[[hardforks]]
+name = "second"
+activation = { block = 20 }
+rules = { gas_limit = 200 }
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(spec.rules_at(9, 0).gas_limit, 0x7fffffff);
+    assert_eq!(spec.rules_at(10, 0).gas_limit, 100);
+    assert_eq!(spec.rules_at(20, 0).gas_limit, 200);
+}
+
+#[test]
+fn test_default_spec() {
+    let spec = TelosChainSpec::default();
+    assert_eq!(spec.rules_at(u32::MAX, u64::MAX), TelosRules::default());
+    // Same signatures as builds without the former bad_sig_padding feature
+    assert!(!spec.rules_at(0, 0).bad_sig_padding);
+    assert!(spec.rules_at(0, 0).allows_tx_type(0));
+    assert!(spec.rules_at(0, 0).allows_tx_type(2));
+    assert!(!spec.rules_at(0, 0).allows_tx_type(1));
+
+    let legacy_only = TelosChainSpec {
+        base: TelosRules {
+            allowed_tx_types: vec![0],
+            ..Default::default()
+        },
+        hardforks: vec![TelosHardfork {
+            name: "eip1559".to_string(),
+            activation: HardforkCondition::Revision(1),
+            rules: TelosRulesOverride {
+                allowed_tx_types: Some(vec![0, 2]),
+                ..Default::default()
+            },
+        }],
+    };
+    assert!(!legacy_only.rules_at(100, 0).allows_tx_type(2));
+    assert!(legacy_only.rules_at(100, 1).allows_tx_type(2));
+}
+
+#[test]
+fn test_sig_padding() {
+    let sender: Address = "0xc51fe232a0153f1f44572369cefe7b90f2ba08a5"
+        .parse()
+        .unwrap();
+
+    let padded = make_unique_vrs(Checksum256::default(), sender, 0, false);
+    let bad_padded = make_unique_vrs(Checksum256::default(), sender, 0, true);
+
+    let padded_s = padded.s().to_be_bytes::<32>();
+    let bad_padded_s = bad_padded.s().to_be_bytes::<32>();
+    assert_eq!(&padded_s[..20], sender.as_slice());
+    assert_eq!(&bad_padded_s[..20], sender.as_slice());
+    assert_eq!(padded_s[20..], [0xff; 12]);
+    assert_eq!(bad_padded_s[20..], [0x00; 12]);
+}
@@ -1 +1,4 @@
+#[allow(dead_code)]
+pub mod mock_http;
+#[allow(dead_code)]
 pub mod test_utils;
@@ -1,5 +1,5 @@
 use alloy::hex::FromHex;
-use alloy::primitives::{Bytes, B256, U256};
+use alloy::primitives::{Address, Bytes, B256, U256};
 use alloy_consensus::{Signed, TxEnvelope, TxLegacy};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -450,6 +450,32 @@ pub fn compare_transaction(
         TxEnvelope::Eip1559(_stx) => compare_1559(&tx.envelope, tx15, trx_index, block_num),
         _ => panic!("not implemented!"),
     }
+
+    if tx15.createdaddr.is_empty() {
+        assert!(
+            tx.created_address.is_none(),
+            "created address difference at tx #{} of evm block #{}",
+            trx_index,
+            block_num
+        );
+    } else {
+        let from = Address::from_str(&tx15.from).unwrap();
+        let nonce = tx15.nonce.parse::<u64>().unwrap();
+        assert_eq!(
+            tx.created_address,
+            Some(Address::from_str(&tx15.createdaddr).unwrap()),
+            "created address difference at tx #{} of evm block #{}",
+            trx_index,
+            block_num
+        );
+        assert_eq!(
+            tx.created_address,
+            Some(from.create(nonce)),
+            "created address is not the CREATE address of the sender at tx #{} of evm block #{}",
+            trx_index,
+            block_num
+        );
+    }
 }
 
 pub fn compare_block(block: &TelosEVMBlock, block15: &TelosEVM15Block) {
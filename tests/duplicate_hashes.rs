@@ -0,0 +1,122 @@
+use alloy::primitives::{keccak256, Address, B256};
+use antelope::chain::checksum::Checksum256;
+use proptest::prelude::*;
+use telos_translator_rs::block::TranslatorWarning;
+use telos_translator_rs::duplicate_hashes::{
+    DuplicateHashConfig, DuplicateHashDetector, TxPosition,
+};
+use telos_translator_rs::transaction::make_unique_vrs;
+
+fn hash(n: u64) -> B256 {
+    keccak256(n.to_be_bytes())
+}
+
+fn duplicate(tx_hash: B256, first: (u32, usize), duplicate: (u32, usize)) -> TranslatorWarning {
+    TranslatorWarning::DuplicateTxHash {
+        tx_hash,
+        first: TxPosition {
+            block_num: first.0,
+            tx_index: first.1,
+        },
+        duplicate: TxPosition {
+            block_num: duplicate.0,
+            tx_index: duplicate.1,
+        },
+    }
+}
+
+#[test]
+fn test_duplicate_hashes() {
+    let mut detector = DuplicateHashDetector::new(&DuplicateHashConfig {
+        window_blocks: 10,
+        expected_transactions: 100,
+    });
+
+    assert!(detector.check_block(1, [hash(1), hash(2)]).is_empty());
+    assert_eq!(
+        detector.check_block(2, [hash(3), hash(1), hash(3)]),
+        vec![
+            duplicate(hash(1), (1, 0), (2, 1)),
+            duplicate(hash(3), (2, 0), (2, 2)),
+        ]
+    );
+
+    // Blocks leave the window 10 blocks later
+    assert!(detector.check_block(11, [hash(2)]).is_empty());
+    assert_eq!(
+        detector.check_block(12, [hash(3), hash(2)]),
+        vec![duplicate(hash(2), (11, 0), (12, 1))]
+    );
+}
+
+#[test]
+fn test_bloom_false_positives() {
+    // Far more hashes than the bloom filter is sized for, its hits are all false positives
+    let mut detector = DuplicateHashDetector::new(&DuplicateHashConfig {
+        window_blocks: 50,
+        expected_transactions: 10,
+    });
+    for block_num in 0..200u32 {
+        let hashes = (0..20).map(|index| hash(block_num as u64 * 20 + index));
+        assert!(detector.check_block(block_num, hashes).is_empty());
+    }
+}
+
+/// Native block ids start with the block number
+fn block_id(block_num: u32, rest: [u8; 28]) -> Checksum256 {
+    let mut id = [0u8; 32];
+    id[..4].copy_from_slice(&block_num.to_be_bytes());
+    id[4..].copy_from_slice(&rest);
+    Checksum256::from_bytes(&id).unwrap()
+}
+
+#[test]
+fn test_unique_vrs_collision_across_blocks() {
+    let sender = Address::repeat_byte(1);
+    let first = block_id(1, [0; 28]);
+    let mut next = [0; 28];
+    next[27] = 1;
+    let second = block_id(1, next);
+    assert_eq!(
+        make_unique_vrs(first, sender, 1, false),
+        make_unique_vrs(second, sender, 0, false)
+    );
+}
+
+type VrsInput = ([u8; 20], u16);
+
+fn vrs_input() -> impl Strategy<Value = VrsInput> {
+    (any::<[u8; 20]>(), any::<u16>())
+}
+
+proptest! {
+    /// Only holds within a block, see test_unique_vrs_collision_across_blocks. The second input
+    /// differs from the first in a single component, or entirely.
+    #[test]
+    fn test_unique_vrs_injective_within_block(
+        block_num in any::<u32>(),
+        rest in any::<[u8; 28]>(),
+        first in vrs_input(),
+        other in vrs_input(),
+        changed in 0..3usize,
+        bad_sig_padding in any::<bool>(),
+    ) {
+        let mut second = first;
+        match changed {
+            0 => second.0 = other.0,
+            1 => second.1 = other.1,
+            _ => second = other,
+        }
+        prop_assume!(first != second);
+
+        let vrs = |(sender, trx_index): VrsInput| {
+            make_unique_vrs(
+                block_id(block_num, rest),
+                Address::from(sender),
+                trx_index as usize,
+                bad_sig_padding,
+            )
+        };
+        prop_assert_ne!(vrs(first), vrs(second));
+    }
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use alloy::primitives::Address;
+use antelope::chain::checksum::{Checksum160, Checksum256};
+use antelope::chain::name::Name;
+use antelope::chain::Encoder;
+use common::mock_http::{dead_url, serve, Request, Response};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use telos_translator_rs::resolver::{AddressResolver, HttpResolver, HttpRetryConfig, ResolveError};
+use telos_translator_rs::types::evm_types::AccountRow;
+
+#[derive(Clone, Copy)]
+enum Behavior {
+    Found,
+    Absent,
+    ServerError,
+    Hang,
+    /// Rows of the indexes in `TABLE` inside the requested bounds
+    Table,
+}
+
+const TABLE: [u64; 5] = [1, 2, 3, 10, 40];
+
+fn account_row(index: u64, address: Address) -> String {
+    let row = AccountRow {
+        index,
+        address: Checksum160::from_bytes(address.as_slice()).unwrap(),
+        account: Name::new("alice"),
+        nonce: 0,
+        code: vec![],
+        balance: Checksum256::default(),
+    };
+    hex::encode(Encoder::pack(&row))
+}
+
+fn rows_response(rows: Vec<String>) -> String {
+    serde_json::json!({ "rows": rows, "more": false, "next_key": "" }).to_string()
+}
+
+/// Bounds are sent either as numbers or as strings
+fn bound(request: &serde_json::Value, name: &str) -> u64 {
+    match &request[name] {
+        serde_json::Value::String(bound) => bound.parse().unwrap(),
+        bound => bound.as_u64().unwrap(),
+    }
+}
+
+fn table_response(body: &[u8]) -> String {
+    let request: serde_json::Value = serde_json::from_slice(body).unwrap();
+    let (lower, upper) = (
+        bound(&request, "lower_bound"),
+        bound(&request, "upper_bound"),
+    );
+    rows_response(
+        TABLE
+            .iter()
+            .filter(|index| (lower..=upper).contains(*index))
+            .map(|&index| account_row(index, Address::repeat_byte(index as u8)))
+            .collect(),
+    )
+}
+
+fn address() -> Address {
+    Address::repeat_byte(0xaa)
+}
+
+fn respond(request: Request, behavior: Behavior) -> Response {
+    Some(match behavior {
+        Behavior::Found => ("200 OK", rows_response(vec![account_row(1, address())])),
+        Behavior::Absent => ("200 OK", rows_response(vec![])),
+        Behavior::Table => ("200 OK", table_response(&request.body)),
+        Behavior::ServerError => (
+            "500 Internal Server Error",
+            r#"{"code":500,"message":"Internal Service Error","error":{"code":3010001,"name":"name_type_exception","what":"Invalid name","details":[]}}"#.to_string(),
+        ),
+        Behavior::Hang => {
+            thread::sleep(Duration::from_secs(5));
+            return None;
+        }
+    })
+}
+
+/// Node answering every get_table_rows request the same way, returns its url and request count
+fn mock_node(behavior: Behavior) -> (String, Arc<AtomicU32>) {
+    serve(move |request| respond(request, behavior))
+}
+
+fn retry() -> HttpRetryConfig {
+    HttpRetryConfig {
+        timeout_ms: 500,
+        attempts: 2,
+        initial_backoff_ms: 10,
+        max_backoff_ms: 20,
+    }
+}
+
+#[tokio::test]
+async fn test_failover() {
+    let (failing, failing_requests) = mock_node(Behavior::ServerError);
+    let (healthy, healthy_requests) = mock_node(Behavior::Found);
+    let resolver = HttpResolver::with_endpoints(&[dead_url(), failing, healthy], retry()).unwrap();
+
+    let alice = Name::new("alice").value();
+    assert_eq!(
+        resolver.address_by_name(alice, 0).await,
+        Ok(Some(address()))
+    );
+    assert_eq!(failing_requests.load(Ordering::SeqCst), 1);
+
+    // The endpoint that answered is tried first from then on
+    assert_eq!(resolver.address_by_index(1, 0).await, Ok(Some(address())));
+    assert_eq!(failing_requests.load(Ordering::SeqCst), 1);
+    assert_eq!(healthy_requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_absent_and_failed() {
+    let (absent, _) = mock_node(Behavior::Absent);
+    let resolver = HttpResolver::with_endpoints(&[absent], retry()).unwrap();
+    assert_eq!(resolver.address_by_index(1, 0).await, Ok(None));
+
+    let (failing, failing_requests) = mock_node(Behavior::ServerError);
+    let resolver = HttpResolver::with_endpoints(&[failing, dead_url()], retry()).unwrap();
+    let result = resolver.address_by_index(1, 0).await;
+    assert!(matches!(
+        result,
+        Err(ResolveError::Unavailable { attempts: 2, .. })
+    ));
+    assert_eq!(failing_requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_timeout() {
+    let (hanging, _) = mock_node(Behavior::Hang);
+    let (healthy, _) = mock_node(Behavior::Found);
+    let resolver = HttpResolver::with_endpoints(
+        &[hanging, healthy],
+        HttpRetryConfig {
+            timeout_ms: 100,
+            ..retry()
+        },
+    )
+    .unwrap();
+    assert_eq!(resolver.address_by_index(1, 0).await, Ok(Some(address())));
+}
+
+#[tokio::test]
+async fn test_batched_indexes() {
+    let (node, requests) = mock_node(Behavior::Table);
+    let resolver = HttpResolver::with_endpoints(&[node], retry()).unwrap();
+
+    // 1 to 10 are close enough for a single ranged query, 40 needs its own
+    let addresses = resolver
+        .addresses_by_indexes(&[40, 1, 3, 10, 5, 3], 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        addresses,
+        [1, 3, 10, 40]
+            .into_iter()
+            .map(|index| (index, Address::repeat_byte(index as u8)))
+            .collect::<HashMap<_, _>>()
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,43 @@
+use telos_translator_rs::log_writer::LogWriter;
+
+#[test]
+fn test_log_writer() {
+    let path = std::env::temp_dir().join(format!("log-writer-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let writer = LogWriter::new(std::fs::File::create(&path).unwrap());
+    writer.append(&1);
+    writer.append(&"two");
+    writer.flush();
+    writer.append(&[3]);
+    drop(writer);
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n\"two\"\n[3]\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_log_writer_error() {
+    use std::fs::OpenOptions;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    // Every write to /dev/full fails with no space left
+    let writer = LogWriter::new(OpenOptions::new().write(true).open("/dev/full").unwrap());
+    assert!(writer.check().is_ok());
+    writer.append(&1);
+    writer.flush();
+
+    let start = Instant::now();
+    while writer.check().is_ok() {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "Write error not reported"
+        );
+        sleep(Duration::from_millis(10));
+    }
+    // Later lines are dropped, the error stays
+    writer.append(&2);
+    assert!(writer.check().is_err());
+}
@@ -0,0 +1,196 @@
+use alloy::hex;
+use alloy::primitives::{Address, Bytes, B256, U256};
+use telos_translator_rs::types::evm_types::{PrintedReceipt, PrintedReceiptError, RevertReason};
+use telos_translator_rs::types::trace_types::{to_call_frames, CallType};
+
+const ITXS_CONSOLE: &str = r#"RCPT{{{"charged_gas":"7a3077c83","trx_index":0,"block":30,"status":1,"epoch":1725384572,"createdaddr":"","gasused":"1d954","logs":[],"output":"","errors":[],"itxs":[{"callType":"call","from":"827cf19fa29bf551318f88332df8cf68dec8a4c4","to":"49f54c5e2301eb9256438123e80762470c2c7ec2","value":"0","gas":"5f5e100","gasUsed":"1d954","input":"a9059cbb","output":"","traceAddress":[],"depth":"0"},{"callType":"delegatecall","from":"49f54c5e2301eb9256438123e80762470c2c7ec2","to":"d80744e16d62c62c5fa2a04b92da3fe6b9efb523","value":"0","gas":"5f5c000","gasUsed":"1234","input":"0x12345678","output":"0x0001","traceAddress":[0],"depth":"1"},{"callType":"call","from":"49f54c5e2301eb9256438123e80762470c2c7ec2","to":"c287dcca8dec632282d5d549bd74d6d3a553e03d","value":"de0b6b3a7640000","gas":"5f5b000","gasUsed":"5208","input":"","output":"","traceAddress":[0,0],"depth":"2"},{"callType":"staticcall","from":"49f54c5e2301eb9256438123e80762470c2c7ec2","to":"c51fe232a0153f1f44572369cefe7b90f2ba08a5","value":"0","gas":"5f5a000","gasUsed":"64","input":"","output":"","traceAddress":[1],"depth":"1"}]}}RCPT"#;
+
+const LOGS_RECEIPT: &str = r#"{"charged_gas":"1a65f5a1b3","trx_index":1,"block":31,"status":1,"epoch":1725384573,"createdaddr":"","gasused":"90f1","logs":[{"address":"49f54c5e2301eb9256438123e80762470c2c7ec2","data":"0000000000000000000000000000000000000000000000000000000000000064","topics":["ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef","","827cf19fa29bf551318f88332df8cf68dec8a4c4"]}],"output":"","errors":null}"#;
+
+fn console(receipt: &str) -> String {
+    format!("RCPT{{{{{receipt}}}}}RCPT")
+}
+
+#[test]
+fn test_parse_typed_receipt() {
+    let receipt = PrintedReceipt::from_console(console(LOGS_RECEIPT)).unwrap();
+
+    assert_eq!(receipt.charged_gas, U256::from(0x1a65f5a1b3u64));
+    assert_eq!(receipt.gas_used, 37105);
+    assert_eq!(receipt.created_address, None);
+    assert!(receipt.output.is_empty());
+    assert!(receipt.errors.is_empty());
+
+    assert_eq!(receipt.logs.len(), 1);
+    let log = &receipt.logs[0];
+    assert_eq!(
+        log.address,
+        "0x49f54c5e2301eb9256438123e80762470c2c7ec2"
+            .parse::<Address>()
+            .unwrap()
+    );
+    let topics = log.data.topics();
+    assert_eq!(topics[1], B256::ZERO);
+    assert_eq!(
+        topics[2],
+        "0x000000000000000000000000827cf19fa29bf551318f88332df8cf68dec8a4c4"
+            .parse::<B256>()
+            .unwrap()
+    );
+    assert_eq!(log.data.data[31], 0x64);
+}
+
+#[test]
+fn test_console_segments() {
+    // Prints made by the contract around the receipt
+    let noisy = format!("debug: start{}debug: end", console(LOGS_RECEIPT));
+    let receipt = PrintedReceipt::from_console(noisy).unwrap();
+    assert_eq!(receipt.trx_index, 1);
+
+    // Last valid segment wins, invalid ones are skipped
+    let second = LOGS_RECEIPT.replace(r#""trx_index":1"#, r#""trx_index":2"#);
+    let several = format!(
+        "{}{}{}",
+        console(LOGS_RECEIPT),
+        console(&second),
+        console("not json")
+    );
+    let receipt = PrintedReceipt::from_console(several).unwrap();
+    assert_eq!(receipt.trx_index, 2);
+
+    assert!(matches!(
+        PrintedReceipt::from_console("no receipt here".to_string()),
+        Err(PrintedReceiptError::NotFound)
+    ));
+    assert!(matches!(
+        PrintedReceipt::from_console(format!("RCPT{{{{{LOGS_RECEIPT}")),
+        Err(PrintedReceiptError::NotFound)
+    ));
+}
+
+#[test]
+fn test_invalid_receipt_fields() {
+    let invalid = [
+        (r#""gasused":"90f1""#, r#""gasused":"xyz""#),
+        (r#""charged_gas":"1a65f5a1b3""#, r#""charged_gas":"0xg""#),
+        (r#""createdaddr":"""#, r#""createdaddr":"not an address""#),
+        (r#""output":"""#, r#""output":"abc""#),
+        (
+            r#""ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef""#,
+            r#""ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef00""#,
+        ),
+        (
+            r#""address":"49f54c5e2301eb9256438123e80762470c2c7ec2""#,
+            r#""address":"0049f54c5e2301eb9256438123e80762470c2c7ec2""#,
+        ),
+    ];
+
+    for (field, replacement) in invalid {
+        let receipt = LOGS_RECEIPT.replace(field, replacement);
+        assert_ne!(receipt, LOGS_RECEIPT);
+        assert!(
+            matches!(
+                PrintedReceipt::from_console(console(&receipt)),
+                Err(PrintedReceiptError::Invalid(_))
+            ),
+            "{replacement}"
+        );
+    }
+}
+
+#[test]
+fn test_parse_itxs() {
+    let receipt = PrintedReceipt::from_console(ITXS_CONSOLE.to_string()).unwrap();
+
+    assert_eq!(receipt.itxs.len(), 4);
+    let itx = &receipt.itxs[2];
+    assert_eq!(itx.call_type, CallType::Call);
+    assert_eq!(
+        itx.from,
+        "0x49f54c5e2301eb9256438123e80762470c2c7ec2"
+            .parse::<Address>()
+            .unwrap()
+    );
+    assert_eq!(
+        itx.to,
+        Some(
+            "0xc287dcca8dec632282d5d549bd74d6d3a553e03d"
+                .parse::<Address>()
+                .unwrap()
+        )
+    );
+    assert_eq!(itx.value, U256::from(1_000_000_000_000_000_000u128));
+    assert_eq!(itx.gas_used, 21000);
+    assert_eq!(itx.trace_address, vec![0, 0]);
+    assert_eq!(itx.depth, 2);
+    assert_eq!(receipt.itxs[1].output, Bytes::from_static(&[0x00, 0x01]));
+}
+
+#[test]
+fn test_lenient_itxs() {
+    // Entries with another layout are skipped, the rest of the receipt is kept
+    let itxs = r#"[{"callType":"call","from":"49f54c5e2301eb9256438123e80762470c2c7ec2","to":"","value":"0","gas":"0","gasUsed":"0","input":"","output":"","traceAddress":[],"depth":"0"},{"type":"call","sender":"49f54c5e2301eb9256438123e80762470c2c7ec2"},{"callType":"call","from":"49f54c5e2301eb9256438123e80762470c2c7ec2","gas":12,"traceAddress":"0"},"not an object"]"#;
+    let receipt = LOGS_RECEIPT.replace(
+        r#""errors":null"#,
+        &format!(r#""errors":null,"itxs":{itxs}"#),
+    );
+    let receipt = PrintedReceipt::from_console(console(&receipt)).unwrap();
+    assert_eq!(receipt.trx_index, 1);
+    assert_eq!(receipt.logs.len(), 1);
+    assert_eq!(receipt.itxs.len(), 1);
+    assert_eq!(receipt.itxs[0].to, None);
+
+    for itxs in ["null", "{}", r#""""#] {
+        let receipt = LOGS_RECEIPT.replace(
+            r#""errors":null"#,
+            &format!(r#""errors":null,"itxs":{itxs}"#),
+        );
+        let receipt = PrintedReceipt::from_console(console(&receipt)).unwrap();
+        assert!(receipt.itxs.is_empty(), "itxs: {itxs}");
+    }
+}
+
+#[test]
+fn test_itxs_call_frames() {
+    let receipt = PrintedReceipt::from_console(ITXS_CONSOLE.to_string()).unwrap();
+    let frames = to_call_frames(&receipt.itxs);
+
+    assert_eq!(frames.len(), 1);
+    let root = &frames[0];
+    assert_eq!(root.typ, "CALL");
+    assert_eq!(root.calls.len(), 2);
+    assert_eq!(root.calls[0].typ, "DELEGATECALL");
+    assert_eq!(root.calls[0].calls.len(), 1);
+    assert_eq!(root.calls[0].calls[0].gas_used, U256::from(21000));
+    assert_eq!(root.calls[1].typ, "STATICCALL");
+    assert!(root.calls[1].calls.is_empty());
+
+    let json = serde_json::to_value(root).unwrap();
+    assert_eq!(json["type"], "CALL");
+    assert_eq!(json["calls"][0]["calls"][0]["type"], "CALL");
+    assert!(json.get("output").is_none());
+}
+
+#[test]
+fn test_revert_reasons() {
+    let error = hex::decode("08c379a0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000124e6f7420656e6f7567682062616c616e63650000000000000000000000000000").unwrap();
+    assert_eq!(
+        RevertReason::decode(&error),
+        Some(RevertReason::Error("Not enough balance".to_string()))
+    );
+
+    let panic =
+        hex::decode("4e487b710000000000000000000000000000000000000000000000000000000000000011")
+            .unwrap();
+    let reason = RevertReason::decode(&panic).unwrap();
+    assert_eq!(reason, RevertReason::Panic(U256::from(0x11)));
+    assert_eq!(reason.to_string(), "panic: 0x11");
+
+    // Custom errors and truncated payloads are not decoded
+    assert_eq!(
+        RevertReason::decode(&hex::decode("32b2baa3").unwrap()),
+        None
+    );
+    assert_eq!(RevertReason::decode(&error[..40]), None);
+    assert_eq!(RevertReason::decode(&[]), None);
+}
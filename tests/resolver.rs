@@ -0,0 +1,267 @@
+use alloy::primitives::{Address, Bytes, B256, U256};
+use antelope::chain::checksum::{Checksum160, Checksum256};
+use antelope::chain::name::Name;
+use std::collections::BTreeMap;
+use telos_translator_rs::address_store::{AddressStore, StoredAddress};
+use telos_translator_rs::block::DecodedRow;
+use telos_translator_rs::resolver::{
+    AddressResolver, MemoryResolver, ResolveError, SnapshotResolver,
+};
+use telos_translator_rs::snapshot::{EvmSnapshot, SnapshotAccount};
+use telos_translator_rs::types::evm_types::{AccountRow, AccountStateRow};
+use telos_translator_rs::types::translator_types::{
+    CacheEviction, LookupStats, NameToAddressCache,
+};
+
+fn account(index: u64, name: &str, address: Address, present: bool) -> DecodedRow {
+    DecodedRow::Account {
+        present,
+        row: AccountRow {
+            index,
+            address: Checksum160::from_bytes(address.as_slice()).unwrap(),
+            account: Name::new_from_str(name),
+            nonce: 0,
+            code: vec![],
+            balance: Checksum256::default(),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_memory_resolver() {
+    let alice = Name::new("alice").value();
+    let resolver = MemoryResolver::new();
+    resolver.record_block(1, &[account(0, "alice", Address::repeat_byte(1), true)]);
+    assert_eq!(
+        resolver.address_by_name(alice, 1).await.unwrap(),
+        Some(Address::repeat_byte(1))
+    );
+    assert_eq!(
+        resolver.address_by_index(0, 1).await.unwrap(),
+        Some(Address::repeat_byte(1))
+    );
+
+    resolver.record_block(2, &[account(0, "alice", Address::repeat_byte(1), false)]);
+    assert_eq!(resolver.address_by_name(alice, 2).await.unwrap(), None);
+    assert_eq!(resolver.address_by_index(0, 2).await.unwrap(), None);
+
+    resolver.record_wallet(alice, Address::repeat_byte(2));
+    assert_eq!(
+        resolver.address_by_name(alice, 3).await.unwrap(),
+        Some(Address::repeat_byte(2))
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_resolver() {
+    let snapshot = EvmSnapshot {
+        block_num: 100,
+        block_id: B256::ZERO,
+        accounts: vec![
+            SnapshotAccount {
+                index: 3,
+                address: Address::repeat_byte(3),
+                account: "bob".to_string(),
+                nonce: 0,
+                balance: U256::ZERO,
+                code: Bytes::new(),
+                storage: BTreeMap::new(),
+            },
+            SnapshotAccount {
+                index: 4,
+                address: Address::repeat_byte(4),
+                account: String::new(),
+                nonce: 1,
+                balance: U256::ZERO,
+                code: Bytes::new(),
+                storage: BTreeMap::new(),
+            },
+        ],
+    };
+    let resolver = SnapshotResolver::new(&snapshot);
+    let bob = Name::new("bob").value();
+
+    // Blocks up to the snapshot can't be answered, which isn't the account missing
+    assert_eq!(
+        resolver.address_by_name(bob, 100).await,
+        Err(ResolveError::BeforeSnapshot {
+            block_num: 100,
+            snapshot_block: 100
+        })
+    );
+    assert_eq!(
+        resolver.address_by_name(bob, 101).await.unwrap(),
+        Some(Address::repeat_byte(3))
+    );
+    assert_eq!(
+        resolver.address_by_index(4, 101).await.unwrap(),
+        Some(Address::repeat_byte(4))
+    );
+    assert_eq!(resolver.address_by_name(0, 101).await.unwrap(), None);
+
+    // Recorded blocks are ignored, rows removed after the snapshot still resolve
+    resolver.record_block(102, &[account(3, "bob", Address::repeat_byte(3), false)]);
+    assert_eq!(
+        resolver.address_by_name(bob, 103).await.unwrap(),
+        Some(Address::repeat_byte(3))
+    );
+}
+
+#[tokio::test]
+async fn test_layered_resolver() {
+    let alice = Name::new("alice").value();
+    let bob = Name::new("bob").value();
+
+    let fallback = MemoryResolver::new();
+    fallback.insert(bob, 1, Address::repeat_byte(1));
+    let cache = NameToAddressCache::with_fallback(fallback);
+
+    cache.record_block(10, &[account(0, "alice", Address::repeat_byte(0xaa), true)]);
+    assert_eq!(
+        cache.address_by_name(alice, 10).await.unwrap(),
+        Some(Address::repeat_byte(0xaa))
+    );
+    assert_eq!(
+        cache.address_by_name(bob, 10).await.unwrap(),
+        Some(Address::repeat_byte(1))
+    );
+    assert_eq!(
+        cache.address_by_name(bob, 11).await.unwrap(),
+        Some(Address::repeat_byte(1))
+    );
+    assert_eq!(cache.address_by_index(7, 11).await.unwrap(), None);
+
+    assert_eq!(
+        cache.stats(),
+        LookupStats {
+            deltas: 1,
+            cache: 1,
+            http: 2
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_address_store() {
+    let path = std::env::temp_dir().join(format!("address-store-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let alice = Name::new("alice").value();
+    let bob = Name::new("bob").value();
+
+    let fallback = MemoryResolver::new();
+    fallback.insert(bob, 1, Address::repeat_byte(1));
+    let cache = NameToAddressCache::with_capacity(fallback, 100, CacheEviction::Lru)
+        .with_store(AddressStore::open(&path).unwrap());
+    cache.record_block(10, &[account(0, "alice", Address::repeat_byte(0xaa), true)]);
+    // Balance updates of the same row are not written again
+    cache.record_block(11, &[account(0, "alice", Address::repeat_byte(0xaa), true)]);
+    cache.record_block(12, &[account(0, "alice", Address::repeat_byte(0xbb), true)]);
+    assert_eq!(
+        cache.address_by_name(bob, 12).await.unwrap(),
+        Some(Address::repeat_byte(1))
+    );
+    drop(cache);
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 5);
+
+    // Warm start, the empty fallback is never needed
+    let mut store = AddressStore::open(&path).unwrap();
+    assert_eq!(
+        store.take_entries(),
+        vec![
+            StoredAddress::Name {
+                name: alice,
+                index: Some(0),
+                address: Address::repeat_byte(0xbb),
+            },
+            StoredAddress::Index {
+                index: 0,
+                address: Address::repeat_byte(0xbb),
+            },
+            StoredAddress::Name {
+                name: bob,
+                index: None,
+                address: Address::repeat_byte(1),
+            },
+        ]
+    );
+    let cache = NameToAddressCache::with_fallback(MemoryResolver::new()).with_store(store);
+    assert_eq!(
+        cache.address_by_name(alice, 13).await.unwrap(),
+        Some(Address::repeat_byte(0xbb))
+    );
+    assert_eq!(
+        cache.address_by_index(0, 13).await.unwrap(),
+        Some(Address::repeat_byte(0xbb))
+    );
+    assert_eq!(
+        cache.address_by_name(bob, 13).await.unwrap(),
+        Some(Address::repeat_byte(1))
+    );
+    assert_eq!(
+        cache.stats(),
+        LookupStats {
+            deltas: 0,
+            cache: 3,
+            http: 0
+        }
+    );
+    drop(cache);
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+    // Removed rows are forgotten from the next block on, by the caches and by the next warm
+    // start
+    let cache = NameToAddressCache::with_fallback(MemoryResolver::new())
+        .with_store(AddressStore::open(&path).unwrap());
+    cache.record_block(
+        14,
+        &[account(0, "alice", Address::repeat_byte(0xbb), false)],
+    );
+    assert_eq!(
+        cache.address_by_index(0, 14).await.unwrap(),
+        Some(Address::repeat_byte(0xbb))
+    );
+    cache.record_block(15, &[]);
+    assert_eq!(cache.address_by_name(alice, 15).await.unwrap(), None);
+    assert_eq!(cache.address_by_index(0, 15).await.unwrap(), None);
+    drop(cache);
+    assert_eq!(
+        AddressStore::open(&path).unwrap().take_entries(),
+        vec![StoredAddress::Name {
+            name: bob,
+            index: None,
+            address: Address::repeat_byte(1),
+        }]
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_self_destruct_block() {
+    let storage = |key: u8, present: bool| DecodedRow::AccountState {
+        present,
+        account_index: 6,
+        row: AccountStateRow {
+            index: key as u64,
+            key: Checksum256::from_bytes(&[key; 32]).unwrap(),
+            value: Checksum256::from_bytes(&[1; 32]).unwrap(),
+        },
+    };
+    // Nothing cached the contract before, and the node head doesn't have its row anymore
+    let cache = NameToAddressCache::with_fallback(MemoryResolver::new());
+    cache.record_block(
+        20,
+        &[
+            account(6, "", Address::repeat_byte(6), false),
+            storage(1, false),
+            storage(2, false),
+        ],
+    );
+    assert_eq!(
+        cache.address_by_index(6, 20).await.unwrap(),
+        Some(Address::repeat_byte(6))
+    );
+    assert_eq!(cache.stats().http, 0);
+
+    cache.record_block(21, &[]);
+    assert_eq!(cache.address_by_index(6, 21).await.unwrap(), None);
+}
@@ -22,6 +22,7 @@ fn test_unsigned_trx() {
             .unwrap(),
             Address::ZERO,
             0,
+            false,
         ),
     );
     if tx.is_err() {
@@ -54,6 +55,7 @@ fn test_unsigned_trx2() {
             .unwrap(),
             Address::ZERO,
             0,
+            false,
         ),
     );
     if tx.is_err() {
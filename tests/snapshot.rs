@@ -0,0 +1,239 @@
+mod common;
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use antelope::api::client::{APIClient, DefaultProvider};
+use antelope::chain::checksum::{Checksum160, Checksum256};
+use antelope::chain::name::Name;
+use antelope::chain::Encoder;
+use common::mock_http::serve;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use telos_translator_rs::block::DecodedRow;
+use telos_translator_rs::snapshot::{EvmSnapshot, GenesisAllocAccount};
+use telos_translator_rs::state::EvmState;
+use telos_translator_rs::types::evm_types::{AccountRow, AccountStateRow};
+
+const HEAD_BLOCK: u32 = 1000;
+
+fn checksum256(value: u64) -> Checksum256 {
+    Checksum256::from_bytes(&U256::from(value).to_be_bytes::<32>()).unwrap()
+}
+
+fn account_row(index: u64, name: &str, nonce: u64, balance: u64, code: &[u8]) -> AccountRow {
+    AccountRow {
+        index,
+        address: Checksum160::from_bytes(Address::left_padding_from(&[index as u8 + 1]).as_slice())
+            .unwrap(),
+        account: Name::new_from_str(name),
+        nonce,
+        code: code.to_vec(),
+        balance: checksum256(balance),
+    }
+}
+
+fn state_row(index: u64, key: u64, value: u64) -> AccountStateRow {
+    AccountStateRow {
+        index,
+        key: checksum256(key),
+        value: checksum256(value),
+    }
+}
+
+struct Tables {
+    accounts: Vec<AccountRow>,
+    storage: Vec<(u64, Vec<AccountStateRow>)>,
+}
+
+fn tables() -> Tables {
+    Tables {
+        accounts: vec![
+            account_row(0, "alice", 1, 1_000, &[]),
+            account_row(1, "", 1, 0, &[0x60, 0x80, 0x60, 0x40]),
+            account_row(2, "bob", 0, 5, &[]),
+        ],
+        storage: vec![(
+            1,
+            vec![state_row(0, 7, 42), state_row(1, 8, 0), state_row(2, 9, 3)],
+        )],
+    }
+}
+
+fn bound(params: &Value, field: &str) -> Option<u64> {
+    match &params[field] {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) if !s.is_empty() => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn page<T>(rows: &[T], params: &Value, key: impl Fn(&T) -> u64) -> Value
+where
+    T: antelope::chain::Packer,
+{
+    let lower_bound = bound(params, "lower_bound").unwrap_or(0);
+    let limit = bound(params, "limit").unwrap_or(10) as usize;
+    let matching: Vec<&T> = rows.iter().filter(|row| key(row) >= lower_bound).collect();
+    json!({
+        "rows": matching
+            .iter()
+            .take(limit)
+            .map(|row| hex::encode(Encoder::pack(*row)))
+            .collect::<Vec<_>>(),
+        "more": matching.len() > limit,
+        "next_key": "",
+    })
+}
+
+fn get_info(head_block_num: u32) -> Value {
+    let head_block_id = format!("{head_block_num:08x}{}", "ab".repeat(28));
+    json!({
+        "server_version": "d133c641",
+        "chain_id": "1eaa0824707c8c16bd25145493bf062aecddfeb56c736f6ba6397f3195f33c9f",
+        "head_block_num": head_block_num,
+        "last_irreversible_block_num": head_block_num,
+        "last_irreversible_block_id": head_block_id,
+        "head_block_id": head_block_id,
+        "head_block_time": "2024-01-01T00:00:00.000",
+        "head_block_producer": "eosio",
+        "virtual_block_cpu_limit": 200000000,
+        "virtual_block_net_limit": 1048576000,
+        "block_cpu_limit": 200000,
+        "block_net_limit": 1048576,
+        "server_version_string": "v5.0.0",
+        "fork_db_head_block_num": head_block_num,
+        "fork_db_head_block_id": head_block_id,
+        "server_full_version_string": "v5.0.0-d133c641",
+        "total_cpu_weight": "0",
+        "total_net_weight": "0",
+        "earliest_available_block_num": 1,
+        "last_irreversible_block_time": "2024-01-01T00:00:00.000"
+    })
+}
+
+/// Minimal nodeos chain API serving the eosio.evm tables, head advances on every get_info
+/// call when `moving_head` is set
+fn mock_node(moving_head: bool) -> String {
+    let head = AtomicU32::new(HEAD_BLOCK);
+    let tables = tables();
+    let (url, _) = serve(move |request| {
+        let params: Value = serde_json::from_slice(&request.body).unwrap_or(Value::Null);
+        let response = match request.path.as_str() {
+            "/v1/chain/get_info" => {
+                let head_block_num = if moving_head {
+                    head.fetch_add(1, Ordering::SeqCst)
+                } else {
+                    head.load(Ordering::SeqCst)
+                };
+                get_info(head_block_num)
+            }
+            "/v1/chain/get_table_rows" => {
+                let scope = params["scope"].as_str().unwrap_or("");
+                match params["table"].as_str() {
+                    Some("account") => page(&tables.accounts, &params, |row| row.index),
+                    Some("accountstate") => {
+                        let rows = tables
+                            .storage
+                            .iter()
+                            .find(|(index, _)| Name::from_u64(*index).to_string() == scope)
+                            .map(|(_, rows)| rows.clone())
+                            .unwrap_or_default();
+                        page(&rows, &params, |row| row.index)
+                    }
+                    _ => json!({ "rows": [], "more": false, "next_key": "" }),
+                }
+            }
+            _ => json!({}),
+        };
+        Some(("200 OK", response.to_string()))
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_snapshot() {
+    let api_client = APIClient::<DefaultProvider>::default_provider(mock_node(false)).unwrap();
+
+    // Page size smaller than both tables to go through the lower_bound paging
+    let snapshot = EvmSnapshot::fetch(&api_client, 2).await.unwrap();
+    assert_eq!(snapshot.block_num, HEAD_BLOCK);
+    assert_eq!(snapshot.accounts.len(), 3);
+    assert_eq!(
+        snapshot
+            .accounts
+            .iter()
+            .map(|account| account.account.as_str())
+            .collect::<Vec<_>>(),
+        vec!["alice", "", "bob"]
+    );
+
+    let contract = &snapshot.accounts[1];
+    assert_eq!(contract.code, Bytes::from(vec![0x60, 0x80, 0x60, 0x40]));
+    assert_eq!(
+        contract.storage,
+        BTreeMap::from([
+            (
+                B256::from(U256::from(7).to_be_bytes::<32>()),
+                U256::from(42)
+            ),
+            (B256::from(U256::from(9).to_be_bytes::<32>()), U256::from(3)),
+        ])
+    );
+
+    let path = std::env::temp_dir().join(format!("evm-snapshot-{}.json", std::process::id()));
+    snapshot.write(&path).unwrap();
+    assert_eq!(EvmSnapshot::read(&path).unwrap(), snapshot);
+    std::fs::remove_file(&path).unwrap();
+
+    // Same state as replaying the rows as table deltas
+    let tables = tables();
+    let mut rows: Vec<DecodedRow> = tables
+        .accounts
+        .into_iter()
+        .map(|row| DecodedRow::Account { present: true, row })
+        .collect();
+    for (account_index, slots) in tables.storage {
+        rows.extend(slots.into_iter().map(|row| DecodedRow::AccountState {
+            present: true,
+            account_index,
+            row,
+        }));
+    }
+    let mut from_deltas = EvmState::default();
+    from_deltas.apply(&rows);
+    assert_eq!(snapshot.to_state().state_root(), from_deltas.state_root());
+}
+
+#[tokio::test]
+async fn test_genesis_alloc() {
+    let api_client = APIClient::<DefaultProvider>::default_provider(mock_node(false)).unwrap();
+    let snapshot = EvmSnapshot::fetch(&api_client, 500).await.unwrap();
+
+    let alloc = serde_json::to_value(snapshot.to_genesis_alloc()).unwrap();
+    let contract = alloc
+        .as_object()
+        .unwrap()
+        .iter()
+        .find(|(address, _)| address.parse::<Address>().unwrap() == snapshot.accounts[1].address)
+        .map(|(_, account)| account)
+        .unwrap();
+    assert_eq!(contract["nonce"], "0x1");
+    assert_eq!(contract["balance"], "0x0");
+    assert_eq!(contract["code"], "0x60806040");
+    assert_eq!(
+        contract["storage"]["0x0000000000000000000000000000000000000000000000000000000000000007"],
+        "0x000000000000000000000000000000000000000000000000000000000000002a"
+    );
+
+    let parsed: BTreeMap<Address, GenesisAllocAccount> = serde_json::from_value(alloc).unwrap();
+    let eoa = &parsed[&snapshot.accounts[0].address];
+    assert_eq!(eoa.balance, U256::from(1_000));
+    assert!(eoa.code.is_none());
+    assert!(eoa.storage.is_empty());
+}
+
+#[tokio::test]
+async fn test_snapshot_head_moved() {
+    let api_client = APIClient::<DefaultProvider>::default_provider(mock_node(true)).unwrap();
+    assert!(EvmSnapshot::fetch(&api_client, 500).await.is_err());
+}
@@ -0,0 +1,282 @@
+use alloy::primitives::{keccak256, Address, B256, U256};
+use alloy_consensus::constants::EMPTY_ROOT_HASH;
+use antelope::chain::checksum::{Checksum160, Checksum256};
+use antelope::chain::name::Name;
+use proptest::prelude::*;
+use reth_primitives::Account;
+use reth_trie_common::root::{state_root_unhashed, storage_root_unhashed};
+use reth_trie_common::TrieAccount;
+use std::collections::{BTreeMap, BTreeSet};
+use telos_translator_rs::block::DecodedRow;
+use telos_translator_rs::state::EvmState;
+use telos_translator_rs::types::evm_types::{AccountRow, AccountStateRow};
+
+const CODE: &[u8] = &[0x60, 0x80, 0x60, 0x40];
+const NO_CODE: &[u8] = &[];
+
+fn address(index: u64) -> Address {
+    Address::left_padding_from(&index.to_be_bytes())
+}
+
+fn slot_key(key: u64) -> B256 {
+    B256::from(U256::from(key).to_be_bytes::<32>())
+}
+
+fn checksum256(value: u64) -> Checksum256 {
+    Checksum256::from_bytes(&U256::from(value).to_be_bytes::<32>()).unwrap()
+}
+
+fn account(index: u64, nonce: u64, balance: u64, code: &[u8], present: bool) -> DecodedRow {
+    DecodedRow::Account {
+        present,
+        row: AccountRow {
+            index,
+            address: Checksum160::from_bytes(address(index).as_slice()).unwrap(),
+            account: Name::new("eosio.evm"),
+            nonce,
+            code: code.to_vec(),
+            balance: checksum256(balance),
+        },
+    }
+}
+
+fn slot(index: u64, key: u64, value: u64, present: bool) -> DecodedRow {
+    DecodedRow::AccountState {
+        present,
+        account_index: index,
+        row: AccountStateRow {
+            index: key,
+            key: checksum256(key),
+            value: checksum256(value),
+        },
+    }
+}
+
+fn expected_root(accounts: Vec<(u64, u64, u64, &[u8], Vec<(u64, u64)>)>) -> B256 {
+    state_root_unhashed(
+        accounts
+            .into_iter()
+            .map(|(index, nonce, balance, code, slots)| {
+                let storage_root = if slots.is_empty() {
+                    EMPTY_ROOT_HASH
+                } else {
+                    storage_root_unhashed(
+                        slots
+                            .into_iter()
+                            .map(|(key, value)| (slot_key(key), U256::from(value))),
+                    )
+                };
+                let account = Account {
+                    nonce,
+                    balance: U256::from(balance),
+                    bytecode_hash: (!code.is_empty()).then(|| keccak256(code)),
+                };
+                (address(index), TrieAccount::from((account, storage_root)))
+            }),
+    )
+}
+
+#[test]
+fn test_empty_state() {
+    let mut state = EvmState::default();
+    assert_eq!(state.state_root(), EMPTY_ROOT_HASH);
+
+    state.apply(&[slot(0, 1, 0, true)]);
+    assert_eq!(state.state_root(), EMPTY_ROOT_HASH);
+}
+
+#[test]
+fn test_state_root() {
+    let mut state = EvmState::default();
+    state.apply(&[
+        account(0, 1, 1_000, NO_CODE, true),
+        account(1, 1, 0, CODE, true),
+        slot(1, 0, 42, true),
+        slot(1, 1, 7, true),
+    ]);
+    assert_eq!(
+        state.state_root(),
+        expected_root(vec![
+            (0, 1, 1_000, NO_CODE, vec![]),
+            (1, 1, 0, CODE, vec![(0, 42), (1, 7)]),
+        ])
+    );
+
+    // Next block: transfer, one slot cleared with SSTORE 0 and one removed row
+    state.apply(&[
+        account(0, 2, 900, NO_CODE, true),
+        slot(1, 0, 0, true),
+        slot(1, 1, 7, false),
+        slot(1, 2, 5, true),
+    ]);
+    assert_eq!(
+        state.state_root(),
+        expected_root(vec![
+            (0, 2, 900, NO_CODE, vec![]),
+            (1, 1, 0, CODE, vec![(2, 5)]),
+        ])
+    );
+
+    // Self destruct removes the account with its storage
+    state.apply(&[slot(1, 2, 5, false), account(1, 1, 0, CODE, false)]);
+    assert_eq!(state.account_count(), 1);
+    assert_eq!(
+        state.state_root(),
+        expected_root(vec![(0, 2, 900, NO_CODE, vec![])])
+    );
+}
+
+#[test]
+fn test_snapshot_matches_deltas() {
+    let mut from_deltas = EvmState::default();
+    from_deltas.apply(&[account(3, 0, 5, CODE, true), slot(3, 9, 1, true)]);
+    from_deltas.apply(&[slot(3, 9, 2, true), slot(3, 10, 3, true)]);
+
+    let row = match account(3, 0, 5, CODE, true) {
+        DecodedRow::Account { row, .. } => row,
+        _ => unreachable!(),
+    };
+    let mut from_snapshot = EvmState::from_rows(
+        vec![row],
+        vec![
+            (3, slot_key(9), U256::from(2)),
+            (3, slot_key(10), U256::from(3)),
+        ],
+    );
+
+    assert_eq!(from_deltas.state_root(), from_snapshot.state_root());
+}
+
+fn moved_account(index: u64, address: Address, nonce: u64) -> DecodedRow {
+    DecodedRow::Account {
+        present: true,
+        row: AccountRow {
+            index,
+            address: Checksum160::from_bytes(address.as_slice()).unwrap(),
+            account: Name::new("eosio.evm"),
+            nonce,
+            code: vec![],
+            balance: checksum256(0),
+        },
+    }
+}
+
+#[test]
+fn test_address_change() {
+    let mut state = EvmState::default();
+    state.apply(&[account(0, 1, 0, NO_CODE, true), slot(0, 1, 9, true)]);
+    state.state_root();
+
+    // The row keeps its index and storage, the old address leaves the trie
+    let moved = Address::repeat_byte(0xee);
+    state.apply(&[moved_account(0, moved, 1)]);
+    let storage_root = storage_root_unhashed([(slot_key(1), U256::from(9))]);
+    let account = Account {
+        nonce: 1,
+        balance: U256::ZERO,
+        bytecode_hash: None,
+    };
+    assert_eq!(
+        state.state_root(),
+        state_root_unhashed([(moved, TrieAccount::from((account, storage_root)))])
+    );
+}
+
+#[derive(Debug, Clone)]
+enum Change {
+    Account { index: u64, address: u8, nonce: u64 },
+    Removal { index: u64 },
+    Slot { index: u64, key: u64, value: u64 },
+}
+
+fn change() -> impl Strategy<Value = Change> {
+    prop_oneof![
+        (0..4u64, 0..3u8, 0..3u64).prop_map(|(index, address, nonce)| Change::Account {
+            index,
+            address,
+            nonce
+        }),
+        (0..4u64).prop_map(|index| Change::Removal { index }),
+        (0..4u64, 0..4u64, 0..3u64).prop_map(|(index, key, value)| Change::Slot {
+            index,
+            key,
+            value
+        }),
+    ]
+}
+
+/// Addresses are unique per index, `address` picks one of a few per index
+fn change_address(index: u64, address: u8) -> Address {
+    Address::left_padding_from(&[address, index as u8])
+}
+
+proptest! {
+    /// Roots taken between blocks of account updates, address changes, removals and storage
+    /// writes match the state rebuilt from scratch
+    #[test]
+    fn test_incremental_state_root(
+        blocks in prop::collection::vec(prop::collection::vec(change(), 1..12), 1..8),
+    ) {
+        let mut state = EvmState::default();
+        let mut accounts: BTreeMap<u64, (Address, u64)> = BTreeMap::new();
+        let mut storage: BTreeMap<u64, BTreeMap<u64, u64>> = BTreeMap::new();
+        for changes in blocks {
+            // Rows of a block are collapsed to their last write, an index gets one account
+            // row per block so the model doesn't have to
+            let mut rows = vec![];
+            let mut account_rows = BTreeSet::new();
+            for change in changes {
+                if let Change::Account { index, .. } | Change::Removal { index } = change {
+                    if !account_rows.insert(index) {
+                        continue;
+                    }
+                }
+                match change {
+                    Change::Account { index, address, nonce } => {
+                        let address = change_address(index, address);
+                        rows.push(moved_account(index, address, nonce));
+                        accounts.insert(index, (address, nonce));
+                    }
+                    Change::Removal { index } => {
+                        let (address, nonce) = accounts
+                            .remove(&index)
+                            .unwrap_or((change_address(index, 0), 0));
+                        let row = match moved_account(index, address, nonce) {
+                            DecodedRow::Account { row, .. } => row,
+                            _ => unreachable!(),
+                        };
+                        rows.push(DecodedRow::Account { present: false, row });
+                        storage.remove(&index);
+                    }
+                    Change::Slot { index, key, value } => {
+                        rows.push(slot(index, key, value, true));
+                        let slots = storage.entry(index).or_default();
+                        if value == 0 {
+                            slots.remove(&key);
+                        } else {
+                            slots.insert(key, value);
+                        }
+                    }
+                }
+            }
+            state.apply(&rows);
+
+            let expected = state_root_unhashed(accounts.iter().map(|(index, (address, nonce))| {
+                let slots = storage.get(index).filter(|slots| !slots.is_empty());
+                let storage_root = slots.map_or(EMPTY_ROOT_HASH, |slots| {
+                    storage_root_unhashed(
+                        slots.iter().map(|(key, value)| (slot_key(*key), U256::from(*value))),
+                    )
+                });
+                let account = Account {
+                    nonce: *nonce,
+                    balance: U256::ZERO,
+                    bytecode_hash: None,
+                };
+                (*address, TrieAccount::from((account, storage_root)))
+            }));
+            prop_assert_eq!(state.account_count(), accounts.len());
+            prop_assert_eq!(state.state_root(), expected);
+        }
+    }
+}
@@ -1,10 +1,19 @@
+use alloy::hex;
+use alloy::primitives::{Address, Parity, Signature, TxKind, U256};
+use alloy_consensus::{SignableTransaction, TxLegacy};
 use antelope::api::client::APIClient;
 use antelope::chain::asset::{Asset, Symbol};
-use antelope::chain::checksum::Checksum256;
+use antelope::chain::checksum::{Checksum160, Checksum256};
 use antelope::chain::name::Name;
 use antelope::util::hex_to_bytes;
-use telos_translator_rs::transaction::TelosEVMTransaction;
-use telos_translator_rs::types::evm_types::{TransferAction, WithdrawAction};
+use k256::ecdsa::SigningKey;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use telos_translator_rs::chain_spec::TelosRules;
+use telos_translator_rs::resolver::{MemoryResolver, ResolveError};
+use telos_translator_rs::transaction::{parse_deposit_memo, NativeOrigin, TelosEVMTransaction};
+use telos_translator_rs::types::evm_types::{
+    PrintedReceipt, RawAction, RevertReason, TransferAction, WithdrawAction,
+};
 use telos_translator_rs::types::translator_types::NameToAddressCache;
 
 #[tokio::test]
@@ -16,15 +25,19 @@ async fn test_deposit() {
             "11e1a6c5c637681588383e401479054882c2a168f1ea766bedcc75a9ca4ce6b8",
         ))
         .unwrap(),
+        0,
         TransferAction {
             from: Name::new("exrsrv.tf"),
             to: Name::new("eosio.evm"),
             quantity: Asset::new(654507, Symbol::new("TLOS", 4)),
             memo: "0xb4b01216a5bc8f1c8a33cd990a1239030e60c905".to_string(),
         },
+        NativeOrigin::default(),
+        &TelosRules::default(),
         &NameToAddressCache::new(APIClient::default()),
     )
-    .await;
+    .await
+    .unwrap();
 
     assert_eq!(
         trx.hash().to_string(),
@@ -32,28 +45,267 @@ async fn test_deposit() {
     );
 }
 
+#[test]
+fn test_deposit_memos() {
+    let lower: Address = "0xb4b01216a5bc8f1c8a33cd990a1239030e60c905"
+        .parse()
+        .unwrap();
+    let checksummed: Address = "0x87bC2200f5066DFc22e987DAb486b979Cd254F4B"
+        .parse()
+        .unwrap();
+
+    let cases = [
+        // Memo of the mainnet deposit in test_deposit
+        ("0xb4b01216a5bc8f1c8a33cd990a1239030e60c905", Some(lower)),
+        ("0xB4B01216A5BC8F1C8A33CD990A1239030E60C905", Some(lower)),
+        (
+            "0x87bC2200f5066DFc22e987DAb486b979Cd254F4B",
+            Some(checksummed),
+        ),
+        (
+            "0x87bc2200f5066dfc22e987dab486b979cd254f4b",
+            Some(checksummed),
+        ),
+        // Checksums are not verified, a broken one still names the address
+        (
+            "0x87Bc2200f5066DFc22e987DAb486b979Cd254F4B",
+            Some(checksummed),
+        ),
+        // Nothing is trimmed and only a lower case prefix counts
+        ("  0xb4b01216a5bc8f1c8a33cd990a1239030e60c905\n", None),
+        ("0XB4B01216A5BC8F1C8A33CD990A1239030E60C905", None),
+        // Missing prefix, wrong length and non hex characters
+        ("b4b01216a5bc8f1c8a33cd990a1239030e60c905", None),
+        ("0xb4b01216a5bc8f1c8a33cd990a1239030e60c9", None),
+        ("0xb4b01216a5bc8f1c8a33cd990a1239030e60c90500", None),
+        ("0xzzb01216a5bc8f1c8a33cd990a1239030e60c905", None),
+        ("0xb4b01216a5bc8f1c8a33cd990a1239030e60c905 deposit", None),
+        ("", None),
+        ("0x", None),
+        ("Deposit to EVM", None),
+    ];
+
+    for (memo, expected) in cases {
+        assert_eq!(parse_deposit_memo(memo), expected, "memo: {memo:?}");
+    }
+}
+
 #[tokio::test]
 async fn test_withdraw() {
     let from = "0x87bC2200f5066DFc22e987DAb486b979Cd254F4B"
         .parse()
         .unwrap();
-    let trx = TelosEVMTransaction::from_withdraw_no_cache(
+    let resolver = MemoryResolver::new();
+    resolver.insert(Name::new("steferretto").value(), 1, from);
+
+    let trx = TelosEVMTransaction::from_withdraw(
         40,
         0,
         Checksum256::from_bytes(&hex_to_bytes(
             "1203ee37cfc4130ea7bf4885f3cbbf1fe85a55ce64709fe7357dcdc453e0ba1f",
         ))
         .unwrap(),
+        0,
         WithdrawAction {
             to: Name::new("steferretto"),
             quantity: Asset::new(37000000, Symbol::new("TLOS", 4)),
         },
-        from,
+        NativeOrigin::default(),
+        &TelosRules::default(),
+        &resolver,
     )
-    .await;
+    .await
+    .unwrap();
 
     assert_eq!(
         trx.hash().to_string(),
         "0x2cac6ea0102c2eb6e3ad4288853c0a2d457643d162ff56d1b381bcb8de1fe9e9"
     );
+
+    // Accounts without a linked address fail the lookup instead of panicking
+    let result = TelosEVMTransaction::from_withdraw(
+        40,
+        0,
+        Checksum256::default(),
+        7,
+        WithdrawAction {
+            to: Name::new("nobody"),
+            quantity: Asset::new(37000000, Symbol::new("TLOS", 4)),
+        },
+        NativeOrigin::default(),
+        &TelosRules::default(),
+        &resolver,
+    )
+    .await;
+    assert_eq!(
+        result.err(),
+        Some(ResolveError::UnknownName {
+            name: Name::new("nobody").value(),
+            block_num: 7,
+        })
+    );
+}
+
+fn unsigned_create_action(sender: Address, nonce: u64) -> RawAction {
+    let tx = TxLegacy {
+        chain_id: None,
+        nonce,
+        gas_price: 0,
+        gas_limit: 1_000_000,
+        to: TxKind::Create,
+        value: U256::ZERO,
+        input: hex::decode("6080604052348015600e575f80fd5b50")
+            .unwrap()
+            .into(),
+    };
+    let mut raw = vec![];
+    tx.encode_for_signing(&mut raw);
+
+    RawAction {
+        ram_payer: Name::new("eosio.evm"),
+        tx: raw,
+        estimate_gas: false,
+        sender: Some(Checksum160::from_bytes(sender.as_slice()).unwrap()),
+    }
+}
+
+fn create_receipt(created_address: Address) -> PrintedReceipt {
+    PrintedReceipt {
+        created_address: Some(created_address),
+        gas_used: 1_000_000,
+        status: 1,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_created_address() {
+    let sender: Address = "0xc51fe232a0153f1f44572369cefe7b90f2ba08a5"
+        .parse()
+        .unwrap();
+    let nonce = 3;
+    let block_hash = Checksum256::from_bytes(&hex_to_bytes(
+        "11e1a6c5c637681588383e401479054882c2a168f1ea766bedcc75a9ca4ce6b8",
+    ))
+    .unwrap();
+
+    let expected = sender.create(nonce);
+    let trx = TelosEVMTransaction::from_raw_action(
+        40,
+        0,
+        block_hash,
+        unsigned_create_action(sender, nonce),
+        create_receipt(expected),
+        NativeOrigin::default(),
+        &TelosRules::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(trx.created_address, Some(expected));
+
+    assert_eq!(trx.sender(), Some(sender));
+    assert_eq!(trx.expected_created_address(), Some(expected));
+
+    // The printed address is kept, the mismatch is reported by the block
+    let mismatch = TelosEVMTransaction::from_raw_action(
+        40,
+        0,
+        block_hash,
+        unsigned_create_action(sender, nonce),
+        create_receipt(sender.create(nonce + 1)),
+        NativeOrigin::default(),
+        &TelosRules::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(mismatch.created_address, Some(sender.create(nonce + 1)));
+    assert_eq!(mismatch.expected_created_address(), Some(expected));
+}
+
+#[tokio::test]
+async fn test_signed_created_address() {
+    let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+    let sender =
+        Address::from_raw_public_key(&key.verifying_key().to_encoded_point(false).as_bytes()[1..]);
+    let nonce = 7;
+    let tx = TxLegacy {
+        chain_id: Some(40),
+        nonce,
+        gas_price: 0,
+        gas_limit: 1_000_000,
+        to: TxKind::Create,
+        value: U256::ZERO,
+        input: hex::decode("6080604052348015600e575f80fd5b50")
+            .unwrap()
+            .into(),
+    };
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(tx.signature_hash().as_slice())
+        .unwrap();
+    let signature = Signature::from_rs_and_parity(
+        U256::from_be_slice(&signature.r().to_bytes()),
+        U256::from_be_slice(&signature.s().to_bytes()),
+        Parity::Eip155(35 + 2 * 40 + recovery_id.is_y_odd() as u64),
+    )
+    .unwrap();
+    let mut raw = vec![];
+    tx.encode_with_signature_fields(&signature, &mut raw);
+    let raw = RawAction {
+        ram_payer: Name::new("eosio.evm"),
+        tx: raw,
+        estimate_gas: false,
+        sender: None,
+    };
+
+    let trx = TelosEVMTransaction::from_raw_action(
+        40,
+        0,
+        Checksum256::default(),
+        raw,
+        create_receipt(sender.create(nonce)),
+        NativeOrigin::default(),
+        &TelosRules::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!trx.unsigned);
+    assert_eq!(trx.sender(), Some(sender));
+    assert_eq!(trx.expected_created_address(), trx.created_address);
+}
+
+#[tokio::test]
+async fn test_revert_output() {
+    let sender: Address = "0xc51fe232a0153f1f44572369cefe7b90f2ba08a5"
+        .parse()
+        .unwrap();
+    let block_hash = Checksum256::from_bytes(&hex_to_bytes(
+        "11e1a6c5c637681588383e401479054882c2a168f1ea766bedcc75a9ca4ce6b8",
+    ))
+    .unwrap();
+    let output = "08c379a0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000124e6f7420656e6f7567682062616c616e63650000000000000000000000000000";
+
+    let trx = TelosEVMTransaction::from_raw_action(
+        40,
+        0,
+        block_hash,
+        unsigned_create_action(sender, 0),
+        PrintedReceipt {
+            gas_used: 0x5c5b,
+            status: 0,
+            output: hex::decode(output).unwrap().into(),
+            errors: vec!["EVM Execution Error: Revert".to_string()],
+            ..Default::default()
+        },
+        NativeOrigin::default(),
+        &TelosRules::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(trx.output.as_ref(), hex::decode(output).unwrap().as_slice());
+    assert_eq!(
+        trx.revert_reason,
+        Some(RevertReason::Error("Not enough balance".to_string()))
+    );
+    assert_eq!(trx.errors, vec!["EVM Execution Error: Revert".to_string()]);
 }